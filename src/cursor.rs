@@ -0,0 +1,84 @@
+use crate::tokenize::{TokenStream, TokenizeError};
+use crate::tokens::TokenRef;
+
+/// A position in a `TokenCursor` that it can be reset back to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Mark(usize);
+
+/// A cursor over the tokens of a `TokenStream` that can look any distance ahead and move back,
+/// as a backtracking parser needs
+/// every token is kept once tokenized, so moving back never tokenizes the source again
+pub struct TokenCursor<'src> {
+    stream: TokenStream<'src>,
+    tokens: Vec<TokenRef<'src>>,
+    // the error that ended the stream, which comes after every token
+    error: Option<TokenizeError>,
+    // the index in tokens of the next token
+    position: usize,
+}
+
+impl<'src> TokenCursor<'src> {
+    pub fn new(stream: TokenStream<'src>) -> TokenCursor<'src> {
+        TokenCursor {
+            stream,
+            tokens: Vec::new(),
+            error: None,
+            position: 0,
+        }
+    }
+
+    /// The position of the next token
+    pub fn mark(&self) -> Mark {
+        Mark(self.position)
+    }
+
+    /// Move back, or forward again, to a position marked by this cursor
+    pub fn reset(&mut self, mark: Mark) {
+        assert!(
+            mark.0 <= self.tokens.len(),
+            "Cannot reset to a mark not made by this cursor"
+        );
+        self.position = mark.0;
+    }
+
+    /// Return the next token without moving past it
+    pub fn peek(&mut self) -> Option<Result<&TokenRef<'src>, TokenizeError>> {
+        self.peek_nth(0)
+    }
+
+    /// Return the token n tokens past the next one without moving past any of them
+    /// the error that ended the stream is found past every token, but nothing is past it
+    pub fn peek_nth(&mut self, n: usize) -> Option<Result<&TokenRef<'src>, TokenizeError>> {
+        let index = self.position + n;
+        while self.tokens.len() <= index && self.error.is_none() {
+            match self.stream.next_token() {
+                Some(Ok(token)) => self.tokens.push(token),
+                Some(Err(e)) => self.error = Some(e),
+                None => break,
+            };
+        }
+        match self.tokens.get(index) {
+            Some(token) => Some(Ok(token)),
+            None if index == self.tokens.len() => self.error.clone().map(Err),
+            None => None,
+        }
+    }
+
+    /// Return the next token and move past it
+    /// an error is returned every time it is reached, as the cursor never moves past it
+    pub fn next_token(&mut self) -> Option<Result<&TokenRef<'src>, TokenizeError>> {
+        let position = self.position;
+        if let Some(Ok(_)) = self.peek_nth(0) {
+            self.position += 1;
+        };
+        match self.tokens.get(position) {
+            Some(token) => Some(Ok(token)),
+            None => self.error.clone().map(Err),
+        }
+    }
+
+    /// Every problem found in the tokens so far, see `TokenStream::diagnostics`
+    pub fn diagnostics(&self) -> &[TokenizeError] {
+        self.stream.diagnostics()
+    }
+}
@@ -0,0 +1,351 @@
+use crate::tokenize::TokenizeError;
+use std::borrow::Cow;
+
+const UTF_8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Python's name for the default source encoding
+pub const DEFAULT_ENCODING: &str = "utf-8";
+
+enum Codec {
+    Utf8,
+    Latin1,
+    Ascii,
+    /// decoded by encoding_rs, except for the bytes Python leaves undefined
+    Other(&'static encoding_rs::Encoding, &'static [u8]),
+}
+
+/// Python codecs, by every name Python knows them, that encoding_rs decodes the same way
+/// encoding_rs resolves WHATWG labels, many of which name a different codec in Python,
+/// so the codecs that are not listed here are unknown, e.g. iso-8859-9, gb2312 or shift_jis
+/// the windows code pages map some bytes to C1 controls that are undefined in Python
+static CODECS: &[(&[&str], &encoding_rs::Encoding, &[u8])] = &[
+    (
+        &["cp1250", "1250", "windows-1250"],
+        &encoding_rs::WINDOWS_1250_INIT,
+        b"\x81\x83\x88\x90\x98",
+    ),
+    (
+        &["cp1251", "1251", "windows-1251"],
+        &encoding_rs::WINDOWS_1251_INIT,
+        b"\x98",
+    ),
+    (
+        &["cp1252", "1252", "windows-1252"],
+        &encoding_rs::WINDOWS_1252_INIT,
+        b"\x81\x8D\x8F\x90\x9D",
+    ),
+    (
+        &["cp1253", "1253", "windows-1253"],
+        &encoding_rs::WINDOWS_1253_INIT,
+        b"\x81\x88\x8A\x8C\x8D\x8E\x8F\x90\x98\x9A\x9C\x9D\x9E\x9F\xAA\xD2\xFF",
+    ),
+    (
+        &["cp1254", "1254", "windows-1254"],
+        &encoding_rs::WINDOWS_1254_INIT,
+        b"\x81\x8D\x8E\x8F\x90\x9D\x9E",
+    ),
+    (
+        &["cp1255", "1255", "windows-1255"],
+        &encoding_rs::WINDOWS_1255_INIT,
+        b"\x81\x8A\x8C\x8D\x8E\x8F\x90\x9A\x9C\x9D\x9E\x9F\xCA\xD9\xDA\xDB\xDC\xDD\xDE\xDF\xFB\xFC\xFF",
+    ),
+    (
+        &["cp1256", "1256", "windows-1256"],
+        &encoding_rs::WINDOWS_1256_INIT,
+        b"",
+    ),
+    (
+        &["cp1257", "1257", "windows-1257"],
+        &encoding_rs::WINDOWS_1257_INIT,
+        b"\x81\x83\x88\x8A\x8C\x90\x98\x9A\x9C\x9F\xA1\xA5",
+    ),
+    (
+        &["cp1258", "1258", "windows-1258"],
+        &encoding_rs::WINDOWS_1258_INIT,
+        b"\x81\x8A\x8D\x8E\x8F\x90\x9A\x9D\x9E",
+    ),
+    (
+        &["cp874"],
+        &encoding_rs::WINDOWS_874_INIT,
+        b"\x81\x82\x83\x84\x86\x87\x88\x89\x8A\x8B\x8C\x8D\x8E\x8F\x90\x98\x99\x9A\x9B\x9C\x9D\x9E\x9F\xDB\xDC\xDD\xDE\xFC\xFD\xFE\xFF",
+    ),
+    (
+        &["iso8859-2", "iso-8859-2", "iso-8859-2-1987", "iso-ir-101", "csisolatin2", "latin2", "l2"],
+        &encoding_rs::ISO_8859_2_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-3", "iso-8859-3", "iso-8859-3-1988", "iso-ir-109", "csisolatin3", "latin3", "l3"],
+        &encoding_rs::ISO_8859_3_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-4", "iso-8859-4", "iso-8859-4-1988", "iso-ir-110", "csisolatin4", "latin4", "l4"],
+        &encoding_rs::ISO_8859_4_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-5", "iso-8859-5", "iso-8859-5-1988", "iso-ir-144", "csisolatincyrillic", "cyrillic"],
+        &encoding_rs::ISO_8859_5_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-6", "iso-8859-6", "iso-8859-6-1987", "iso-ir-127", "csisolatinarabic", "arabic", "asmo-708", "ecma-114"],
+        &encoding_rs::ISO_8859_6_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-7", "iso-8859-7", "iso-8859-7-1987", "iso-ir-126", "csisolatingreek", "greek", "greek8", "ecma-118", "elot-928"],
+        &encoding_rs::ISO_8859_7_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-8", "iso-8859-8", "iso-8859-8-1988", "iso-ir-138", "csisolatinhebrew", "hebrew"],
+        &encoding_rs::ISO_8859_8_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-10", "iso-8859-10", "iso-8859-10-1992", "iso-ir-157", "csisolatin6", "latin6", "l6"],
+        &encoding_rs::ISO_8859_10_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-13", "iso-8859-13", "latin7", "l7"],
+        &encoding_rs::ISO_8859_13_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-14", "iso-8859-14", "iso-8859-14-1998", "iso-ir-199", "iso-celtic", "latin8", "l8"],
+        &encoding_rs::ISO_8859_14_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-15", "iso-8859-15", "latin9", "l9"],
+        &encoding_rs::ISO_8859_15_INIT,
+        b"",
+    ),
+    (
+        &["iso8859-16", "iso-8859-16", "iso-8859-16-2001", "iso-ir-226", "latin10", "l10"],
+        &encoding_rs::ISO_8859_16_INIT,
+        b"",
+    ),
+    (&["koi8-r", "cskoi8r"], &encoding_rs::KOI8_R_INIT, b""),
+    (
+        &["cp866", "866", "csibm866", "ibm866"],
+        &encoding_rs::IBM866_INIT,
+        b"",
+    ),
+    (
+        &["mac-roman", "macroman", "macintosh"],
+        &encoding_rs::MACINTOSH_INIT,
+        b"",
+    ),
+    (
+        &["mac-cyrillic", "maccyrillic"],
+        &encoding_rs::X_MAC_CYRILLIC_INIT,
+        b"",
+    ),
+    (
+        &["cp949", "949", "ms949", "uhc"],
+        &encoding_rs::EUC_KR_INIT,
+        b"",
+    ),
+];
+
+/// Normalize an encoding name the same way CPython's tokenize does
+/// only the most common spellings of utf-8 and latin-1 are normalized
+fn normal_name(name: &str) -> String {
+    let enc: String = name
+        .chars()
+        .take(12)
+        .map(|c| {
+            if c == '_' {
+                '-'
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .collect();
+    if enc == "utf-8" || enc.starts_with("utf-8-") {
+        return String::from("utf-8");
+    };
+    for latin in ["latin-1", "iso-8859-1", "iso-latin-1"] {
+        if enc == latin || enc.starts_with(&format!("{}-", latin)) {
+            return String::from("iso-8859-1");
+        };
+    }
+    String::from(name)
+}
+
+/// Find the codec for a Python encoding name
+fn lookup(name: &str) -> Option<Codec> {
+    let label = name.to_ascii_lowercase().replace('_', "-");
+    match label.as_str() {
+        "utf-8" | "utf8" | "u8" | "utf" | "cp65001" => Some(Codec::Utf8),
+        "iso-8859-1" | "iso8859-1" | "8859" | "cp819" | "latin" | "latin-1" | "latin1" | "l1" => {
+            Some(Codec::Latin1)
+        }
+        "ascii" | "us-ascii" | "646" => Some(Codec::Ascii),
+        _ => CODECS
+            .iter()
+            .find(|(names, _, _)| names.contains(&label.as_str()))
+            .map(|(_, encoding, undefined)| Codec::Other(encoding, undefined)),
+    }
+}
+
+/// return the (line, column) immediately after the decoded text
+fn end_of(decoded: &str) -> (usize, usize) {
+    let line = decoded.matches('\n').count() + 1;
+    let column = match decoded.rfind('\n') {
+        Some(index) => decoded[index + 1..].chars().count(),
+        None => decoded.chars().count(),
+    };
+    (line, column)
+}
+
+/// Search one line of source for a PEP 263 coding cookie
+/// the line must look like `[ \t\f]*#.*coding[:=][ \t]*([-\w.]+)`
+fn find_cookie(line: &[u8]) -> Option<String> {
+    let comment = line
+        .iter()
+        .position(|b| !matches!(b, b' ' | b'\t' | b'\x0C'))
+        .filter(|start| line[*start] == b'#')?;
+    let line = &line[comment..];
+    let found = (0..line.len()).find(|index| {
+        line[*index..].starts_with(b"coding")
+            && matches!(line.get(index + "coding".len()), Some(b':' | b'='))
+    })?;
+    let name: String = line[found + "coding:".len()..]
+        .iter()
+        .skip_while(|b| matches!(b, b' ' | b'\t'))
+        .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+        .map(|b| *b as char)
+        .collect();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// A line without code may be followed by a line containing the coding cookie
+fn is_blank_or_comment(line: &[u8]) -> bool {
+    matches!(
+        line.iter().find(|b| !matches!(b, b' ' | b'\t' | b'\x0C')),
+        None | Some(b'#' | b'\r' | b'\n')
+    )
+}
+
+/// Split the first line from the source, keeping its line ending
+fn split_line(source: &[u8]) -> (&[u8], &[u8]) {
+    match source.iter().position(|b| *b == b'\n' || *b == b'\r') {
+        Some(end) if source[end..].starts_with(b"\r\n") => source.split_at(end + 2),
+        Some(end) => source.split_at(end + 1),
+        None => (source, &[]),
+    }
+}
+
+/// Determine the encoding that should be used to decode the Python source
+/// the same way as CPython's `tokenize.detect_encoding`
+/// a UTF-8 byte order mark is reported as "utf-8-sig"
+pub fn detect_encoding(source: &[u8]) -> Result<String, TokenizeError> {
+    let bom_found = source.starts_with(UTF_8_BOM);
+    let source = source.strip_prefix(UTF_8_BOM).unwrap_or(source);
+    let (first, rest) = split_line(source);
+    let (second, _) = split_line(rest);
+
+    let mut cookie = find_cookie(first).map(|name| (name, 1));
+    if cookie.is_none() && is_blank_or_comment(first) {
+        cookie = find_cookie(second).map(|name| (name, 2));
+    };
+
+    match cookie {
+        None if bom_found => Ok(String::from("utf-8-sig")),
+        None => Ok(String::from(DEFAULT_ENCODING)),
+        Some((name, line)) => {
+            let encoding = normal_name(&name);
+            if lookup(&encoding).is_none() {
+                return Err(TokenizeError::UnknownEncoding {
+                    encoding,
+                    start: (line, 0),
+                });
+            };
+            if bom_found {
+                if encoding != "utf-8" {
+                    return Err(TokenizeError::BomEncodingMismatch {
+                        encoding,
+                        start: (line, 0),
+                    });
+                };
+                return Ok(String::from("utf-8-sig"));
+            };
+            Ok(encoding)
+        }
+    }
+}
+
+/// Decode Python source into text
+/// returns the detected encoding, as given by `detect_encoding`, and the decoded source
+/// the decoded source is borrowed whenever the bytes are already valid UTF-8
+pub fn decode_source(source: &[u8]) -> Result<(String, Cow<'_, str>), TokenizeError> {
+    let encoding = detect_encoding(source)?;
+    let source = source.strip_prefix(UTF_8_BOM).unwrap_or(source);
+    let undecodable = |decoded: &str| TokenizeError::UndecodableSource {
+        encoding: encoding.clone(),
+        start: end_of(decoded),
+    };
+
+    let decoded = match lookup(encoding.trim_end_matches("-sig")) {
+        Some(Codec::Utf8) => match std::str::from_utf8(source) {
+            Ok(decoded) => Cow::Borrowed(decoded),
+            Err(e) => {
+                // the prefix up to the error is known to be valid
+                let valid = String::from_utf8_lossy(&source[..e.valid_up_to()]);
+                return Err(undecodable(&valid));
+            }
+        },
+        // ascii is always valid UTF-8
+        Some(Codec::Latin1) if source.is_ascii() => {
+            Cow::Borrowed(std::str::from_utf8(source).unwrap())
+        }
+        Some(Codec::Latin1) => Cow::Owned(source.iter().map(|b| *b as char).collect()),
+        Some(Codec::Ascii) => match source.iter().position(|b| !b.is_ascii()) {
+            Some(bad) => {
+                let valid: String = source[..bad].iter().map(|b| *b as char).collect();
+                return Err(undecodable(&valid));
+            }
+            None => Cow::Borrowed(std::str::from_utf8(source).unwrap()),
+        },
+        Some(Codec::Other(codec, undefined)) => {
+            // only the source before the first undefined byte can be decoded
+            let defined = source
+                .iter()
+                .position(|b| undefined.contains(b))
+                .unwrap_or(source.len());
+            let (source, rest) = source.split_at(defined);
+            match codec.decode_without_bom_handling_and_without_replacement(source) {
+                Some(decoded) if rest.is_empty() => decoded,
+                Some(decoded) => return Err(undecodable(&decoded)),
+                None => {
+                    // decode again, this time only to find where the problem is
+                    let mut decoder = codec.new_decoder_without_bom_handling();
+                    let mut decoded = String::with_capacity(
+                        decoder
+                            .max_utf8_buffer_length_without_replacement(source.len())
+                            .unwrap_or(source.len()),
+                    );
+                    let _ =
+                        decoder.decode_to_string_without_replacement(source, &mut decoded, true);
+                    return Err(undecodable(&decoded));
+                }
+            }
+        }
+        None => {
+            return Err(TokenizeError::UnknownEncoding {
+                encoding,
+                start: (1, 0),
+            })
+        }
+    };
+    Ok((encoding, decoded))
+}
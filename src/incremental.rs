@@ -0,0 +1,229 @@
+use crate::tokenize::{indent_widths, LineState, TokenStream, TokenizeError, TokenizerOptions};
+use crate::tokens::{Token, TokenType};
+use std::ops::Range;
+
+/// A change made to source that was already tokenized
+/// the bytes in `range` of the old source were replaced by `replacement`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit<'a> {
+    pub range: Range<usize>,
+    pub replacement: &'a str,
+}
+
+/// What was done to bring tokens up to date with an edit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retokenized {
+    /// the range of the updated tokens that were tokenized again
+    /// every other token was carried over, moved to its new position
+    pub relexed: Range<usize>,
+    /// problems found in the relexed tokens when recovering
+    pub diagnostics: Vec<TokenizeError>,
+}
+
+impl TextEdit<'_> {
+    /// the byte offset in the new source of old_index, which must be past the edit
+    fn moved(&self, old_index: usize) -> usize {
+        old_index - self.range.len() + self.replacement.len()
+    }
+
+    /// the byte offset in the old source of new_index, which must be past the edit
+    fn moved_back(&self, new_index: usize) -> usize {
+        new_index - self.replacement.len() + self.range.len()
+    }
+}
+
+/// The widths of the line's indent up to index
+fn line_indent(source: &str, index: usize) -> (usize, usize) {
+    let line_start = source[..index]
+        .rfind(['\n', '\r'])
+        .map_or(0, |newline| newline + 1);
+    indent_widths(&source[line_start..index])
+}
+
+/// Replays the tokens of a logical line to keep track of the state between lines
+struct Replay {
+    // the width of every open indent, unless it is not known
+    indents: Vec<Option<(usize, usize)>>,
+    async_def_indents: Option<usize>,
+}
+
+impl Replay {
+    fn new() -> Replay {
+        Replay {
+            indents: vec![Some((0, 0))],
+            async_def_indents: None,
+        }
+    }
+
+    /// Follow the token at index, where the widths of an INDENT can be found from width
+    fn step<F>(&mut self, tokens: &[Token], index: usize, width: F)
+    where
+        F: Fn(&Token) -> Option<(usize, usize)>,
+    {
+        let token = &tokens[index];
+        match token.token_type {
+            TokenType::INDENT => self.indents.push(width(token)),
+            TokenType::DEDENT => {
+                self.indents.pop();
+            }
+            TokenType::ASYNC => {
+                let within =
+                    matches!(self.async_def_indents, Some(indents) if self.indents.len() > indents);
+                let def_follows = tokens[index + 1..]
+                    .iter()
+                    .find(|next| {
+                        !matches!(
+                            next.token_type,
+                            TokenType::WHITESPACE | TokenType::CONTINUATION
+                        )
+                    })
+                    .is_some_and(|next| next.token_contents == "def");
+                if !within && def_follows {
+                    self.async_def_indents = Some(self.indents.len());
+                };
+            }
+            _ => (),
+        };
+    }
+
+    /// The state carried into the next logical line, unless some of it is not known
+    fn line_state(&self) -> Option<LineState> {
+        Some(LineState {
+            indents: self.indents.iter().copied().collect::<Option<Vec<_>>>()?,
+            async_def_indents: self
+                .async_def_indents
+                .filter(|indents| self.indents.len() > *indents),
+        })
+    }
+}
+
+/// Move a token that was carried over from before an edit to its place after it
+fn shift(token: &mut Token, bytes: isize, chars: isize, lines: isize) {
+    let by = |index: usize, delta: isize| (index as isize + delta) as usize;
+    token.byte_start = by(token.byte_start, bytes);
+    token.byte_end = by(token.byte_end, bytes);
+    token.col_start = by(token.col_start, chars);
+    token.col_end = by(token.col_end, chars);
+    token.start.0 = by(token.start.0, lines);
+    token.end.0 = by(token.end.0, lines);
+}
+
+/// Bring the tokens of a source up to date with an edit to it
+/// tokens must be every token of the old source and source is the new source, after the edit
+///
+/// Tokenizing restarts at the start of the logical line before the edit and stops at the end of the
+/// first logical line after the edit where the new tokens agree with the old ones again.
+/// The tokens after that are kept and only moved to their new position.
+/// When an error is found the tokens are left as they were.
+pub fn retokenize(
+    source: &str,
+    tokens: &mut Vec<Token>,
+    edit: &TextEdit,
+    options: TokenizerOptions,
+) -> Result<Retokenized, TokenizeError> {
+    let (edit_start, edit_end) = (edit.range.start, edit.range.end);
+    let new_edit_end = edit_start + edit.replacement.len();
+    // a logical line after a NEWLINE that ends before the edit is tokenized as it was
+    // the NEWLINE itself must be untouched by the edit, so that "\r" does not become "\r\n"
+    let first = tokens
+        .iter()
+        .take_while(|token| token.token_type == TokenType::ENCODING)
+        .count();
+    // when recovering, the tokens after an ERRORTOKEN may have been found inside a string it failed
+    // to start, NEWLINEs included, so tokenizing restarts before the first one before the edit
+    let before_error = tokens[first..]
+        .iter()
+        .position(|token| {
+            token.token_type == TokenType::ERRORTOKEN && token.byte_start < edit_start
+        })
+        .map_or(tokens.len(), |error| first + error);
+    let restart = tokens[first..before_error]
+        .iter()
+        .rposition(|token| token.token_type == TokenType::NEWLINE && token.byte_end < edit_start)
+        .map_or(first, |newline| first + newline + 1);
+
+    let mut replay = Replay::new();
+    for index in first..restart {
+        replay.step(tokens, index, |indent| {
+            Some(line_indent(source, indent.byte_end))
+        });
+    }
+    let restart_byte = match restart {
+        0 => 0,
+        _ => tokens[restart - 1].byte_end,
+    };
+    let restart_state = replay
+        .line_state()
+        .expect("the indents before an edit are always known");
+    let mut stream = TokenStream::resume(source, options, restart_byte, restart_state);
+
+    let mut relexed: Vec<Token> = Vec::new();
+    // the next old token to replay on the way to where the new tokens may agree with the old
+    let mut old = restart;
+    let mut resync: Option<usize> = None;
+    while let Some(token) = stream.next_token() {
+        let token = Token::from(token?);
+        // the empty NEWLINE ending the source is found only once the tokens queued at the end,
+        // such as DEDENTs, have been decided, so the state after it cannot be compared
+        let newline_past_edit = token.token_type == TokenType::NEWLINE
+            && token.byte_start >= new_edit_end
+            && !token.token_contents.is_empty();
+        relexed.push(token);
+        if !newline_past_edit {
+            continue;
+        };
+        let token = relexed.last().unwrap();
+        let old_byte = edit.moved_back(token.byte_start);
+        while old < tokens.len() && tokens[old].byte_start < old_byte {
+            replay.step(tokens, old, |indent| {
+                if !indent.token_contents.is_empty() {
+                    Some(indent_widths(&indent.token_contents))
+                } else if indent.byte_end <= edit_start {
+                    Some(line_indent(source, indent.byte_end))
+                } else if indent.byte_start >= edit_end {
+                    // an INDENT added when recovering is empty, so its width is found from its line
+                    // which is only as it was when the whole line is past the edit
+                    let index = edit.moved(indent.byte_end);
+                    let line_start = source[..index]
+                        .rfind(['\n', '\r'])
+                        .map_or(0, |newline| newline + 1);
+                    (line_start > new_edit_end).then(|| line_indent(source, index))
+                } else {
+                    None
+                }
+            });
+            old += 1;
+        }
+        let agrees = tokens.get(old).is_some_and(|previous| {
+            previous.token_type == TokenType::NEWLINE
+                && previous.byte_start == old_byte
+                && previous.token_contents == token.token_contents
+        });
+        if agrees && replay.line_state() == Some(stream.line_state()) {
+            resync = Some(old);
+            break;
+        };
+    }
+
+    let relexed_len = relexed.len();
+    match resync {
+        Some(newline) => {
+            let old_newline = &tokens[newline];
+            let new_newline = relexed.last().unwrap();
+            let bytes = new_newline.byte_end as isize - old_newline.byte_end as isize;
+            let chars = new_newline.col_end as isize - old_newline.col_end as isize;
+            let lines = new_newline.start.0 as isize - old_newline.start.0 as isize;
+            for token in tokens[newline + 1..].iter_mut() {
+                shift(token, bytes, chars, lines);
+            }
+            tokens.splice(restart..=newline, relexed);
+        }
+        None => {
+            tokens.splice(restart.., relexed);
+        }
+    };
+    Ok(Retokenized {
+        relexed: restart..restart + relexed_len,
+        diagnostics: stream.diagnostics().to_vec(),
+    })
+}
@@ -0,0 +1,246 @@
+/// What a name means to Python, before considering where it is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameKind {
+    /// a name that can only ever be used as a keyword, like `if` or `def`
+    Keyword,
+    /// a name that is a keyword only in some statements, like `match`, and an identifier elsewhere
+    SoftKeyword,
+    /// a name bound in the builtins module, like `len` or `ValueError`
+    Builtin,
+    /// any other name
+    Identifier,
+}
+
+const OLDEST: (u8, u8) = (3, 0);
+
+// a keyword, with the first version it is a keyword in and the first it no longer is
+type Versioned = (&'static str, (u8, u8), Option<(u8, u8)>);
+
+// every keyword in sorted order
+const KEYWORDS: [Versioned; 36] = [
+    ("False", OLDEST, None),
+    ("None", OLDEST, None),
+    ("True", OLDEST, None),
+    // only reserved while the new parser could still be switched off
+    ("__peg_parser__", (3, 9), Some((3, 10))),
+    ("and", OLDEST, None),
+    ("as", OLDEST, None),
+    ("assert", OLDEST, None),
+    ("async", (3, 7), None),
+    ("await", (3, 7), None),
+    ("break", OLDEST, None),
+    ("class", OLDEST, None),
+    ("continue", OLDEST, None),
+    ("def", OLDEST, None),
+    ("del", OLDEST, None),
+    ("elif", OLDEST, None),
+    ("else", OLDEST, None),
+    ("except", OLDEST, None),
+    ("finally", OLDEST, None),
+    ("for", OLDEST, None),
+    ("from", OLDEST, None),
+    ("global", OLDEST, None),
+    ("if", OLDEST, None),
+    ("import", OLDEST, None),
+    ("in", OLDEST, None),
+    ("is", OLDEST, None),
+    ("lambda", OLDEST, None),
+    ("nonlocal", OLDEST, None),
+    ("not", OLDEST, None),
+    ("or", OLDEST, None),
+    ("pass", OLDEST, None),
+    ("raise", OLDEST, None),
+    ("return", OLDEST, None),
+    ("try", OLDEST, None),
+    ("while", OLDEST, None),
+    ("with", OLDEST, None),
+    ("yield", OLDEST, None),
+];
+
+// every soft keyword in sorted order
+// async and await were only keywords following `async def` before they became hard keywords
+const SOFT_KEYWORDS: [Versioned; 6] = [
+    ("_", (3, 10), None),
+    ("async", (3, 5), Some((3, 7))),
+    ("await", (3, 5), Some((3, 7))),
+    ("case", (3, 10), None),
+    ("match", (3, 10), None),
+    ("type", (3, 12), None),
+];
+
+// every public name in the builtins module in sorted order, with the first version it is found in
+const BUILTINS: [(&str, (u8, u8)); 144] = [
+    ("ArithmeticError", OLDEST),
+    ("AssertionError", OLDEST),
+    ("AttributeError", OLDEST),
+    ("BaseException", OLDEST),
+    ("BaseExceptionGroup", (3, 11)),
+    ("BlockingIOError", (3, 3)),
+    ("BrokenPipeError", (3, 3)),
+    ("BufferError", OLDEST),
+    ("BytesWarning", OLDEST),
+    ("ChildProcessError", (3, 3)),
+    ("ConnectionAbortedError", (3, 3)),
+    ("ConnectionError", (3, 3)),
+    ("ConnectionRefusedError", (3, 3)),
+    ("ConnectionResetError", (3, 3)),
+    ("DeprecationWarning", OLDEST),
+    ("EOFError", OLDEST),
+    ("Ellipsis", OLDEST),
+    ("EncodingWarning", (3, 10)),
+    ("EnvironmentError", OLDEST),
+    ("Exception", OLDEST),
+    ("ExceptionGroup", (3, 11)),
+    ("FileExistsError", (3, 3)),
+    ("FileNotFoundError", (3, 3)),
+    ("FloatingPointError", OLDEST),
+    ("FutureWarning", OLDEST),
+    ("GeneratorExit", OLDEST),
+    ("IOError", OLDEST),
+    ("ImportError", OLDEST),
+    ("ImportWarning", OLDEST),
+    ("IndentationError", OLDEST),
+    ("IndexError", OLDEST),
+    ("InterruptedError", (3, 3)),
+    ("IsADirectoryError", (3, 3)),
+    ("KeyError", OLDEST),
+    ("KeyboardInterrupt", OLDEST),
+    ("LookupError", OLDEST),
+    ("MemoryError", OLDEST),
+    ("ModuleNotFoundError", (3, 6)),
+    ("NameError", OLDEST),
+    ("NotADirectoryError", (3, 3)),
+    ("NotImplemented", OLDEST),
+    ("NotImplementedError", OLDEST),
+    ("OSError", OLDEST),
+    ("OverflowError", OLDEST),
+    ("PendingDeprecationWarning", OLDEST),
+    ("PermissionError", (3, 3)),
+    ("ProcessLookupError", (3, 3)),
+    ("PythonFinalizationError", (3, 13)),
+    ("RecursionError", (3, 5)),
+    ("ReferenceError", OLDEST),
+    ("ResourceWarning", (3, 2)),
+    ("RuntimeError", OLDEST),
+    ("RuntimeWarning", OLDEST),
+    ("StopAsyncIteration", (3, 5)),
+    ("StopIteration", OLDEST),
+    ("SyntaxError", OLDEST),
+    ("SyntaxWarning", OLDEST),
+    ("SystemError", OLDEST),
+    ("SystemExit", OLDEST),
+    ("TabError", OLDEST),
+    ("TimeoutError", (3, 3)),
+    ("TypeError", OLDEST),
+    ("UnboundLocalError", OLDEST),
+    ("UnicodeDecodeError", OLDEST),
+    ("UnicodeEncodeError", OLDEST),
+    ("UnicodeError", OLDEST),
+    ("UnicodeTranslateError", OLDEST),
+    ("UnicodeWarning", OLDEST),
+    ("UserWarning", OLDEST),
+    ("ValueError", OLDEST),
+    ("Warning", OLDEST),
+    ("ZeroDivisionError", OLDEST),
+    ("__build_class__", OLDEST),
+    ("__debug__", OLDEST),
+    ("__import__", OLDEST),
+    ("abs", OLDEST),
+    ("aiter", (3, 10)),
+    ("all", OLDEST),
+    ("anext", (3, 10)),
+    ("any", OLDEST),
+    ("ascii", OLDEST),
+    ("bin", OLDEST),
+    ("bool", OLDEST),
+    ("breakpoint", (3, 7)),
+    ("bytearray", OLDEST),
+    ("bytes", OLDEST),
+    ("callable", (3, 2)),
+    ("chr", OLDEST),
+    ("classmethod", OLDEST),
+    ("compile", OLDEST),
+    ("complex", OLDEST),
+    ("delattr", OLDEST),
+    ("dict", OLDEST),
+    ("dir", OLDEST),
+    ("divmod", OLDEST),
+    ("enumerate", OLDEST),
+    ("eval", OLDEST),
+    ("exec", OLDEST),
+    ("filter", OLDEST),
+    ("float", OLDEST),
+    ("format", OLDEST),
+    ("frozenset", OLDEST),
+    ("getattr", OLDEST),
+    ("globals", OLDEST),
+    ("hasattr", OLDEST),
+    ("hash", OLDEST),
+    ("hex", OLDEST),
+    ("id", OLDEST),
+    ("input", OLDEST),
+    ("int", OLDEST),
+    ("isinstance", OLDEST),
+    ("issubclass", OLDEST),
+    ("iter", OLDEST),
+    ("len", OLDEST),
+    ("list", OLDEST),
+    ("locals", OLDEST),
+    ("map", OLDEST),
+    ("max", OLDEST),
+    ("memoryview", OLDEST),
+    ("min", OLDEST),
+    ("next", OLDEST),
+    ("object", OLDEST),
+    ("oct", OLDEST),
+    ("open", OLDEST),
+    ("ord", OLDEST),
+    ("pow", OLDEST),
+    ("print", OLDEST),
+    ("property", OLDEST),
+    ("range", OLDEST),
+    ("repr", OLDEST),
+    ("reversed", OLDEST),
+    ("round", OLDEST),
+    ("set", OLDEST),
+    ("setattr", OLDEST),
+    ("slice", OLDEST),
+    ("sorted", OLDEST),
+    ("staticmethod", OLDEST),
+    ("str", OLDEST),
+    ("sum", OLDEST),
+    ("super", OLDEST),
+    ("tuple", OLDEST),
+    ("type", OLDEST),
+    ("vars", OLDEST),
+    ("zip", OLDEST),
+];
+
+/// Whether name is in the sorted table for the given version
+fn listed(table: &[Versioned], name: &str, python_version: (u8, u8)) -> bool {
+    match table.binary_search_by_key(&name, |(listed, _, _)| listed) {
+        Ok(found) => {
+            let (_, since, until) = table[found];
+            python_version >= since && until.is_none_or(|until| python_version < until)
+        }
+        Err(_) => false,
+    }
+}
+
+/// Classify a name as the given (major, minor) version of Python would
+/// a name that is both a soft keyword and a builtin, like `type`, is a soft keyword
+pub fn classify_name(name: &str, python_version: (u8, u8)) -> NameKind {
+    if listed(&KEYWORDS, name, python_version) {
+        NameKind::Keyword
+    } else if listed(&SOFT_KEYWORDS, name, python_version) {
+        NameKind::SoftKeyword
+    } else if let Ok(found) = BUILTINS.binary_search_by_key(&name, |(builtin, _)| builtin) {
+        if python_version >= BUILTINS[found].1 {
+            NameKind::Builtin
+        } else {
+            NameKind::Identifier
+        }
+    } else {
+        NameKind::Identifier
+    }
+}
@@ -1,2 +1,9 @@
+pub mod cursor;
+pub mod encoding;
+pub mod incremental;
+pub mod keywords;
+pub mod numbers;
+pub mod offsets;
+pub mod strings;
 pub mod tokenize;
 pub mod tokens;
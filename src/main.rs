@@ -1,3 +1,10 @@
+pub mod cursor;
+pub mod encoding;
+pub mod incremental;
+pub mod keywords;
+pub mod numbers;
+pub mod offsets;
+pub mod strings;
 pub mod tokenize;
 pub mod tokens;
 
@@ -23,16 +30,13 @@ if not "":
             token_type,
             exact_token_type,
             token_contents,
-            col_start,
-            col_end,
+            start,
+            end,
+            ..
         } = found.unwrap();
         println!(
-            "{}-{}:\t\t{:?}:{:?}\t\t{:?}",
-            col_start,
-            (col_end - col_start),
-            token_type,
-            exact_token_type,
-            token_contents
+            "{},{}-{},{}:\t\t{:?}:{:?}\t\t{:?}",
+            start.0, start.1, end.0, end.1, token_type, exact_token_type, token_contents
         )
     }
 }
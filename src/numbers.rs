@@ -0,0 +1,39 @@
+use num_bigint::BigUint;
+
+/// The value of a numeric literal
+/// literals are never negative, a leading minus sign is an operator
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumberValue {
+    Integer(BigUint),
+    Float(f64),
+    /// the imaginary part of a complex number whose real part is zero
+    Imaginary(f64),
+}
+
+/// Evaluate the contents of a NUMBER token
+/// floats are rounded to the nearest f64, ties to even, as Python rounds them
+/// None when the contents are not a numeric literal
+pub(crate) fn parse_number(contents: &str) -> Option<NumberValue> {
+    let digits: String = contents.chars().filter(|c| *c != '_').collect();
+    let radix = match digits.get(..2) {
+        Some("0b" | "0B") => 2,
+        Some("0o" | "0O") => 8,
+        Some("0x" | "0X") => 16,
+        _ => 10,
+    };
+    if radix != 10 {
+        return BigUint::parse_bytes(&digits.as_bytes()[2..], radix).map(NumberValue::Integer);
+    };
+    if let Some(imaginary) = digits.strip_suffix(['j', 'J']) {
+        return imaginary.parse().ok().map(NumberValue::Imaginary);
+    };
+    if digits.contains(['.', 'e', 'E']) {
+        // an exponent too large for f64 is infinite, as it is in Python
+        return digits.parse().ok().map(NumberValue::Float);
+    };
+    // only zero may have leading zeros, though compatible mode still returns 007 as a NUMBER
+    if digits.starts_with('0') && digits.contains(|c: char| c != '0') {
+        return None;
+    };
+    BigUint::parse_bytes(digits.as_bytes(), 10).map(NumberValue::Integer)
+}
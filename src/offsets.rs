@@ -0,0 +1,137 @@
+/// A unit that offsets into source text can be counted in
+/// tokens are positioned in `Char`s and sliced in `Byte`s; editors often count in `Utf16` code units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Byte,
+    Utf16,
+    Char,
+}
+
+/// The offsets of a single non-ascii character in each unit
+#[derive(Debug, Clone, Copy)]
+struct Anchor {
+    byte: usize,
+    utf16: usize,
+    char: usize,
+    width: char,
+}
+
+impl Anchor {
+    fn offset(&self, unit: Unit) -> usize {
+        match unit {
+            Unit::Byte => self.byte,
+            Unit::Utf16 => self.utf16,
+            Unit::Char => self.char,
+        }
+    }
+
+    fn width(&self, unit: Unit) -> usize {
+        match unit {
+            Unit::Byte => self.width.len_utf8(),
+            Unit::Utf16 => self.width.len_utf16(),
+            Unit::Char => 1,
+        }
+    }
+}
+
+/// Convert offsets into one source between bytes, UTF-16 code units and characters
+/// ascii characters are the same size in every unit, so only the other characters are recorded
+#[derive(Debug, Clone)]
+pub struct SourceOffsets {
+    anchors: Vec<Anchor>,
+    // the byte offset of the start of every line
+    line_starts: Vec<usize>,
+    len: [usize; 3],
+}
+
+impl SourceOffsets {
+    pub fn new(source: &str) -> Self {
+        let mut anchors = Vec::new();
+        let mut line_starts = vec![0];
+        let mut utf16 = 0;
+        let mut chars = 0;
+        let mut previous = '\0';
+        for (byte, c) in source.char_indices() {
+            if !c.is_ascii() {
+                anchors.push(Anchor {
+                    byte,
+                    utf16,
+                    char: chars,
+                    width: c,
+                });
+            };
+            match c {
+                // a \r\n line ending only starts one line
+                '\n' if previous == '\r' => *line_starts.last_mut().unwrap() = byte + 1,
+                '\n' | '\r' => line_starts.push(byte + 1),
+                _ => (),
+            };
+            utf16 += c.len_utf16();
+            chars += 1;
+            previous = c;
+        }
+        SourceOffsets {
+            anchors,
+            line_starts,
+            len: [source.len(), utf16, chars],
+        }
+    }
+
+    /// The length of the whole source counted in unit
+    pub fn len(&self, unit: Unit) -> usize {
+        self.len[unit as usize]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len[0] == 0
+    }
+
+    /// Convert an offset counted in one unit to the same offset counted in another
+    /// returns None if the offset is past the end of the source or falls inside a character
+    pub fn convert(&self, offset: usize, from: Unit, to: Unit) -> Option<usize> {
+        if offset > self.len(from) {
+            return None;
+        };
+        let before = self
+            .anchors
+            .partition_point(|anchor| anchor.offset(from) <= offset);
+        if before == 0 {
+            return Some(offset);
+        };
+        let anchor = &self.anchors[before - 1];
+        let past = offset - anchor.offset(from);
+        if past == 0 {
+            Some(anchor.offset(to))
+        } else if past < anchor.width(from) {
+            None
+        } else {
+            Some(anchor.offset(to) + anchor.width(to) + past - anchor.width(from))
+        }
+    }
+
+    /// Convert a (line, column) position with its column counted in one unit
+    /// to the same position with its column counted in another
+    /// lines start at 1 and columns at 0, the same as token positions
+    /// returns None if the position is not within the source or falls inside a character
+    pub fn convert_position(
+        &self,
+        position: (usize, usize),
+        from: Unit,
+        to: Unit,
+    ) -> Option<(usize, usize)> {
+        let (line, column) = position;
+        let line_start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let line_end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.len(Unit::Byte));
+        let start_from = self.convert(line_start, Unit::Byte, from)?;
+        let offset = start_from + column;
+        if offset > self.convert(line_end, Unit::Byte, from)? {
+            return None;
+        };
+        let start_to = self.convert(line_start, Unit::Byte, to)?;
+        Some((line, self.convert(offset, from, to)? - start_to))
+    }
+}
@@ -0,0 +1,386 @@
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// What the prefix of a string literal asks for
+/// a string without a prefix has every flag unset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StringPrefix {
+    /// `r`: backslashes are kept as they are
+    pub raw: bool,
+    /// `b`: the literal is bytes rather than str
+    pub bytes: bool,
+    /// `f`: the literal is an f-string with replacement fields
+    pub format: bool,
+    /// `u`: the literal is str, as it would be anyway
+    pub unicode: bool,
+    /// `t`: the literal is a template string with replacement fields
+    pub template: bool,
+}
+
+/// Whether c can be part of a string prefix in some version of Python
+pub(crate) fn is_prefix_letter(c: char) -> bool {
+    matches!(c, 'b' | 'B' | 'f' | 'F' | 'r' | 'R' | 't' | 'T' | 'u' | 'U')
+}
+
+impl StringPrefix {
+    /// Parse the letters before the opening quote of a string literal
+    /// None when the given (major, minor) version of Python does not accept them as a prefix
+    pub fn parse(letters: &str, python_version: (u8, u8)) -> Option<StringPrefix> {
+        let mut prefix = StringPrefix::default();
+        for c in letters.chars() {
+            let seen = match c {
+                'b' | 'B' => &mut prefix.bytes,
+                'f' | 'F' => &mut prefix.format,
+                'r' | 'R' => &mut prefix.raw,
+                't' | 'T' => &mut prefix.template,
+                'u' | 'U' => &mut prefix.unicode,
+                _ => return None,
+            };
+            if *seen {
+                return None;
+            };
+            *seen = true;
+        }
+        let kinds = [prefix.bytes, prefix.format, prefix.template, prefix.unicode];
+        if kinds.iter().filter(|kind| **kind).count() > 1 || prefix.unicode && prefix.raw {
+            return None;
+        };
+        // `rb` was only accepted alongside `br` from 3.3
+        let raw_first = letters.starts_with(['r', 'R']);
+        let since = if prefix.template {
+            (3, 14)
+        } else if prefix.format {
+            (3, 6)
+        } else if prefix.unicode || prefix.bytes && raw_first {
+            (3, 3)
+        } else {
+            (3, 0)
+        };
+        (python_version >= since).then_some(prefix)
+    }
+}
+
+/// The value of a string literal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StringValue {
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+/// The category of warning Python issues about a suspicious escape sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningCategory {
+    DeprecationWarning,
+    SyntaxWarning,
+}
+
+/// An escape sequence that Python keeps, but warns about
+/// every warning carries the (line, column) of the backslash that starts the escape
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapeWarning {
+    pub category: WarningCategory,
+    /// the escape sequence as written, including the backslash
+    pub escape: String,
+    /// whether the escape is an octal escape beyond \377, rather than an unknown escape
+    pub octal: bool,
+    pub start: (usize, usize),
+}
+
+impl fmt::Display for EscapeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.octal {
+            true => "invalid octal escape sequence",
+            false => "invalid escape sequence",
+        };
+        write!(
+            f,
+            "{:?}: {} '{}' (line {}, column {})",
+            self.category, kind, self.escape, self.start.0, self.start.1
+        )
+    }
+}
+
+/// A string literal together with every warning Python issues while evaluating it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedString {
+    pub value: StringValue,
+    /// like CPython, only the first invalid escape in the literal is warned about
+    pub warnings: Vec<EscapeWarning>,
+}
+
+/// Problems that prevent a string literal from being evaluated
+/// every variant carries the (line, column) where the problem started
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// the \x, \u or \U escape at `start` has too few hex digits after it
+    TruncatedEscape { escape: char, start: (usize, usize) },
+    /// the \N escape at `start` is not followed by a name in braces
+    MalformedCharacterName { start: (usize, usize) },
+    /// the \N escape at `start` names no Unicode character
+    UnknownCharacterName { name: String, start: (usize, usize) },
+    /// the \U escape at `start` is past the last Unicode codepoint
+    IllegalCharacter { start: (usize, usize) },
+    /// the escape at `start` is a lone surrogate, which Python allows but a Rust String cannot hold
+    Surrogate {
+        codepoint: u32,
+        start: (usize, usize),
+    },
+    /// the char at `start` of a bytes literal is not ascii
+    NonAsciiBytes { start: (usize, usize) },
+}
+
+impl DecodeError {
+    /// the (line, column) where the problem started
+    pub fn start(&self) -> (usize, usize) {
+        match self {
+            DecodeError::TruncatedEscape { start, .. }
+            | DecodeError::MalformedCharacterName { start }
+            | DecodeError::UnknownCharacterName { start, .. }
+            | DecodeError::IllegalCharacter { start }
+            | DecodeError::Surrogate { start, .. }
+            | DecodeError::NonAsciiBytes { start } => *start,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            DecodeError::TruncatedEscape { escape, .. } => {
+                let digits = hex_digits(*escape);
+                format!("truncated \\{}{} escape", escape, "X".repeat(digits))
+            }
+            DecodeError::MalformedCharacterName { .. } => {
+                String::from("malformed \\N character escape")
+            }
+            DecodeError::UnknownCharacterName { name, .. } => {
+                format!("unknown Unicode character name '{}'", name)
+            }
+            DecodeError::IllegalCharacter { .. } => String::from("illegal Unicode character"),
+            DecodeError::Surrogate { codepoint, .. } => {
+                format!(
+                    "surrogate U+{:04X} cannot be held in a Rust string",
+                    codepoint
+                )
+            }
+            DecodeError::NonAsciiBytes { .. } => {
+                String::from("bytes can only contain ASCII literal characters")
+            }
+        };
+        let (line, column) = self.start();
+        write!(f, "{} (line {}, column {})", message, line, column)
+    }
+}
+
+impl Error for DecodeError {}
+
+/// How many hex digits follow the letter of a hex escape
+fn hex_digits(escape: char) -> usize {
+    match escape {
+        'x' => 2,
+        'u' => 4,
+        _ => 8,
+    }
+}
+
+/// The chars of a literal, tracking the (line, column) of each as the tokenizer does
+struct Literal<'a> {
+    chars: Peekable<Chars<'a>>,
+    position: (usize, usize),
+}
+
+impl Literal<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        match c {
+            // a \r\n pair is a single line ending
+            '\n' => self.position = (self.position.0 + 1, 0),
+            '\r' if self.chars.peek() != Some(&'\n') => self.position = (self.position.0 + 1, 0),
+            _ => self.position.1 += 1,
+        };
+        Some(c)
+    }
+
+    /// the next char, only when it passes the test
+    fn next_if(&mut self, test: impl Fn(char) -> bool) -> Option<char> {
+        match self.chars.peek() {
+            Some(c) if test(*c) => self.next(),
+            _ => None,
+        }
+    }
+}
+
+impl StringValue {
+    /// add c to the value, which must be ascii when the value is bytes
+    fn push(&mut self, c: char) {
+        match self {
+            StringValue::Str(value) => value.push(c),
+            StringValue::Bytes(value) => value.push(c as u8),
+        };
+    }
+}
+
+/// Evaluate the contents of a STRING token with the given prefix as the given (major, minor)
+/// version of Python would
+/// `start` is the (line, column) of the token, so that problems can be placed in the source
+pub(crate) fn decode_string(
+    contents: &str,
+    prefix: StringPrefix,
+    start: (usize, usize),
+    python_version: (u8, u8),
+) -> Result<DecodedString, DecodeError> {
+    let prefix_len = contents.find(['\'', '"']).unwrap_or(0);
+    let quote = &contents[prefix_len..prefix_len + 1];
+    let quote_len = match contents[prefix_len..].starts_with(&quote.repeat(3)) {
+        true => 3,
+        false => 1,
+    };
+    let body = &contents[prefix_len + quote_len..contents.len() - quote_len];
+    let mut literal = Literal {
+        chars: body.chars().peekable(),
+        position: (start.0, start.1 + prefix_len + quote_len),
+    };
+    let mut value = match prefix.bytes {
+        true => StringValue::Bytes(Vec::new()),
+        false => StringValue::Str(String::new()),
+    };
+    let mut warnings = Vec::new();
+    let category = match python_version >= (3, 12) {
+        true => WarningCategory::SyntaxWarning,
+        false => WarningCategory::DeprecationWarning,
+    };
+
+    loop {
+        let start = literal.position;
+        let c = match literal.next() {
+            Some(c) => c,
+            None => break,
+        };
+        if prefix.bytes && !c.is_ascii() {
+            return Err(DecodeError::NonAsciiBytes { start });
+        };
+        if c == '\r' {
+            // every line ending in the source is read as \n
+            literal.next_if(|c| c == '\n');
+            value.push('\n');
+            continue;
+        };
+        if c != '\\' || prefix.raw {
+            value.push(c);
+            continue;
+        };
+        let escaped = match literal.next() {
+            Some(escaped) => escaped,
+            None => {
+                value.push('\\');
+                break;
+            }
+        };
+        match escaped {
+            // the string continues on the next line
+            '\n' => (),
+            '\r' => {
+                literal.next_if(|c| c == '\n');
+            }
+            '\\' | '\'' | '"' => value.push(escaped),
+            'a' => value.push('\u{7}'),
+            'b' => value.push('\u{8}'),
+            'f' => value.push('\u{C}'),
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            'v' => value.push('\u{B}'),
+            '0'..='7' => {
+                let mut digits = String::from(escaped);
+                while digits.len() < 3 {
+                    match literal.next_if(|c| c.is_digit(8)) {
+                        Some(digit) => digits.push(digit),
+                        None => break,
+                    };
+                }
+                let code = u32::from_str_radix(&digits, 8).unwrap();
+                if code > 0o377 && python_version >= (3, 11) && warnings.is_empty() {
+                    warnings.push(EscapeWarning {
+                        category,
+                        escape: format!("\\{}", digits),
+                        octal: true,
+                        start,
+                    });
+                };
+                match &mut value {
+                    StringValue::Str(value) => value.push(char::from_u32(code).unwrap()),
+                    StringValue::Bytes(value) => value.push(code as u8),
+                };
+            }
+            'x' | 'u' | 'U' if escaped == 'x' || !prefix.bytes => {
+                let mut digits = String::new();
+                while digits.len() < hex_digits(escaped) {
+                    match literal.next_if(|c| c.is_ascii_hexdigit()) {
+                        Some(digit) => digits.push(digit),
+                        None => {
+                            return Err(DecodeError::TruncatedEscape {
+                                escape: escaped,
+                                start,
+                            })
+                        }
+                    };
+                }
+                let code = u32::from_str_radix(&digits, 16).unwrap();
+                match &mut value {
+                    StringValue::Bytes(value) => value.push(code as u8),
+                    StringValue::Str(value) => match char::from_u32(code) {
+                        Some(c) => value.push(c),
+                        None if code > 0x10FFFF => {
+                            return Err(DecodeError::IllegalCharacter { start })
+                        }
+                        None => {
+                            return Err(DecodeError::Surrogate {
+                                codepoint: code,
+                                start,
+                            })
+                        }
+                    },
+                };
+            }
+            'N' if !prefix.bytes => {
+                if literal.next_if(|c| c == '{').is_none() {
+                    return Err(DecodeError::MalformedCharacterName { start });
+                };
+                let mut name = String::new();
+                while let Some(c) = literal.next_if(|c| c != '}' && c != '\n' && c != '\r') {
+                    name.push(c);
+                }
+                if name.is_empty() || literal.next_if(|c| c == '}').is_none() {
+                    return Err(DecodeError::MalformedCharacterName { start });
+                };
+                match unicode_names2::character(&name) {
+                    Some(c) => value.push(c),
+                    None => return Err(DecodeError::UnknownCharacterName { name, start }),
+                };
+            }
+            _ => {
+                // an unknown escape is kept as it was written
+                if prefix.bytes && !escaped.is_ascii() {
+                    return Err(DecodeError::NonAsciiBytes {
+                        start: (start.0, start.1 + 1),
+                    });
+                };
+                // CPython reads a backslash before a non-ASCII char as only a backslash
+                if python_version >= (3, 6) && escaped.is_ascii() && warnings.is_empty() {
+                    warnings.push(EscapeWarning {
+                        category,
+                        escape: format!("\\{}", escaped),
+                        octal: false,
+                        start,
+                    });
+                };
+                value.push('\\');
+                value.push(escaped);
+            }
+        };
+    }
+
+    Ok(DecodedString { value, warnings })
+}
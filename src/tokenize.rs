@@ -1,132 +1,947 @@
-use crate::tokens::{Token, TokenType};
+use crate::cursor::TokenCursor;
+use crate::encoding;
+use crate::strings::{is_prefix_letter, StringPrefix};
+use crate::tokens::{split_type_comment, Token, TokenRef, TokenType};
+use std::borrow::Cow;
 use std::cmp;
 use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, ErrorKind};
+use std::ops::Range;
 
 use unicode_categories::UnicodeCategories;
 
-struct RawSource {
-    input: Vec<char>,
+// the widest peek made by any tokenizing rule
+const MAX_PEEK: usize = 3;
+
+// how much already tokenized input read from a reader is kept before it is dropped
+const MAX_CONSUMED: usize = 64 * 1024;
+
+struct RawSource<'src> {
+    input: Cow<'src, str>,
+    // the cursor and lookahead are byte indices into input
+    // the char index of each is kept alongside, as this is what tokens report
     cursor: usize,
+    cursor_chars: usize,
     lookahead: usize,
+    lookahead_chars: usize,
+    // how far the lookahead has been peeked past the end of input
+    overrun: usize,
+    // the chars returned by the last peek
+    peeked: [char; MAX_PEEK],
+    // char index of the first char of every physical line committed so far
+    // lines that were dropped along with the input they started are only counted
+    line_starts: Vec<usize>,
+    lines_dropped: usize,
+    // where the rest of the input is read from, one line at a time, until it is exhausted
+    reader: Option<Box<dyn BufRead + 'src>>,
+    lines_read: usize,
+    // how many bytes were dropped from the front of input after being tokenized
+    discarded: usize,
+    // a problem reading the input, waiting to be returned in place of the next token
+    read_error: Option<TokenizeError>,
 }
 
-impl RawSource {
-    pub fn new(input: &str) -> RawSource {
+impl<'src> RawSource<'src> {
+    pub fn new(input: Cow<'src, str>) -> RawSource<'src> {
         RawSource {
-            input: input.chars().collect(),
+            input,
             cursor: 0,
+            cursor_chars: 0,
             lookahead: 0,
+            lookahead_chars: 0,
+            overrun: 0,
+            peeked: ['\0'; MAX_PEEK],
+            line_starts: vec![0],
+            lines_dropped: 0,
+            reader: None,
+            lines_read: 0,
+            discarded: 0,
+            read_error: None,
+        }
+    }
+
+    pub fn from_reader(reader: Box<dyn BufRead + 'src>) -> RawSource<'src> {
+        RawSource {
+            reader: Some(reader),
+            ..RawSource::new(Cow::Owned(String::new()))
         }
     }
 
-    fn at_end(&self) -> bool {
+    fn at_end(&mut self) -> bool {
+        self.fill(1);
         self.cursor >= self.input.len()
     }
 
+    /// read lines until there are at least window chars past the lookahead
+    /// or the reader is exhausted
+    fn fill(&mut self, window: usize) {
+        while let Some(reader) = self.reader.as_mut() {
+            if self.input[self.lookahead..].chars().take(window).count() >= window {
+                return;
+            };
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => self.reader = None,
+                Ok(_) => {
+                    self.lines_read += 1;
+                    match String::from_utf8(line) {
+                        Ok(line) => self.append(&line),
+                        Err(e) => {
+                            let valid = &e.as_bytes()[..e.utf8_error().valid_up_to()];
+                            let column = String::from_utf8_lossy(valid).chars().count();
+                            self.read_error = Some(TokenizeError::UndecodableSource {
+                                encoding: String::from("utf-8"),
+                                start: (self.lines_read, column),
+                            });
+                            self.reader = None;
+                        }
+                    };
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => {
+                    self.read_error = Some(TokenizeError::UnreadableSource {
+                        message: e.to_string(),
+                        start: (self.lines_read + 1, 0),
+                    });
+                    self.reader = None;
+                }
+            };
+        }
+    }
+
+    /// add a line read from the reader to the input
+    /// dropping whatever was tokenized long enough ago, so the input does not grow without bound
+    fn append(&mut self, line: &str) {
+        // a byte order mark is not part of the source
+        let line = match self.lines_read {
+            1 => line.strip_prefix('\u{feff}').unwrap_or(line),
+            _ => line,
+        };
+        if self.cursor > MAX_CONSUMED {
+            // the last char consumed is kept, as it decides the position past the end of input
+            let kept = self.input[..self.cursor]
+                .chars()
+                .next_back()
+                .map_or(0, char::len_utf8);
+            let dropped = self.cursor - kept;
+            self.input.to_mut().drain(..dropped);
+            self.cursor -= dropped;
+            self.lookahead -= dropped;
+            self.discarded += dropped;
+            let current_line = self
+                .line_starts
+                .partition_point(|start| *start <= self.cursor_chars);
+            self.line_starts.drain(..current_line - 1);
+            self.lines_dropped += current_line - 1;
+        };
+        self.input.to_mut().push_str(line);
+    }
+
     fn committed_index(&self) -> usize {
-        self.cursor
+        self.cursor_chars
+    }
+
+    fn committed_byte_index(&self) -> usize {
+        self.discarded + self.cursor
     }
 
     fn peeked_index(&self) -> usize {
-        self.lookahead
+        self.lookahead_chars + self.overrun
     }
 
     /// return a slice of input stream of size window or the rest of the input
     /// starting from the last peek
     fn peek(&mut self, window: usize) -> &[char] {
-        let window_open = cmp::min(self.lookahead, self.input.len());
-        let window_close = cmp::min(self.lookahead + window, self.input.len());
-        self.lookahead += window;
-        &self.input[window_open..window_close]
+        assert!(
+            window <= MAX_PEEK,
+            "Cannot peek more than {} chars",
+            MAX_PEEK
+        );
+        let mut found = 0;
+        if self.overrun == 0 {
+            self.fill(window);
+            for c in self.input[self.lookahead..].chars().take(window) {
+                self.peeked[found] = c;
+                self.lookahead += c.len_utf8();
+                found += 1;
+            }
+            self.lookahead_chars += found;
+        };
+        self.overrun += window - found;
+        &self.peeked[..found]
     }
 
     fn hide(&mut self, window: usize) {
-        self.lookahead -= window;
-        assert!(
-            self.lookahead >= self.cursor,
-            "Cannot hide what was already consumed"
-        );
+        let past_end = cmp::min(window, self.overrun);
+        self.overrun -= past_end;
+        for _ in past_end..window {
+            let last = self.input[..self.lookahead].chars().next_back();
+            assert!(
+                self.lookahead > self.cursor && last.is_some(),
+                "Cannot hide what was already consumed"
+            );
+            self.lookahead -= last.map_or(0, char::len_utf8);
+            self.lookahead_chars -= 1;
+        }
     }
 
     fn commit(&mut self) {
-        self.cursor = cmp::min(self.lookahead, self.input.len());
+        let bytes = self.input.as_bytes();
+        let mut index = self.cursor_chars;
+        for c in self.input[self.cursor..self.lookahead].chars() {
+            index += 1;
+            match c {
+                '\n' => self.line_starts.push(index),
+                // a \r\n pair is a single line ending
+                '\r' if bytes.get(self.cursor + 1) != Some(&b'\n') => self.line_starts.push(index),
+                _ => (),
+            };
+            self.cursor += c.len_utf8();
+        }
+        self.cursor_chars = index;
+    }
+
+    /// commit everything up to the byte at index without tokenizing it
+    fn skip_to(&mut self, index: usize) {
+        self.lookahead_chars += self.input[self.lookahead..index].chars().count();
+        self.lookahead = index;
+        self.overrun = 0;
+        self.commit();
     }
 
     fn revert(&mut self) {
         self.lookahead = self.cursor;
+        self.lookahead_chars = self.cursor_chars;
+        self.overrun = 0;
+    }
+
+    /// return the (line, column) of the char at index
+    /// lines are counted from 1 and columns from 0, as CPython does
+    /// only valid for indices that have already been committed
+    fn position(&self, index: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= index);
+        (
+            self.lines_dropped + line,
+            index - self.line_starts[line - 1],
+        )
+    }
+
+    /// return the (line, column) just past the last char of the slice start..end
+    /// a slice ending in a newline ends on the same line as the newline
+    fn end_position(&self, start: usize, end: usize) -> (usize, usize) {
+        if end > start {
+            let (line, column) = self.position(end - 1);
+            (line, column + 1)
+        } else {
+            self.position(start)
+        }
+    }
+
+    /// return the (line, column) at which any tokens past the end of the input are found
+    /// this is always the start of the line following the last, unless the last is empty
+    fn eof_position(&self) -> (usize, usize) {
+        match self.input.chars().next_back() {
+            Some(c) if c != '\n' && c != '\r' => {
+                (self.lines_dropped + self.line_starts.len() + 1, 0)
+            }
+            _ => self.position(self.cursor_chars),
+        }
+    }
+
+    /// return everything peeked past the cursor
+    /// this borrows from the input whenever the input is itself borrowed
+    fn peeked_str(&self) -> Cow<'src, str> {
+        match &self.input {
+            Cow::Borrowed(input) => Cow::Borrowed(&input[self.cursor..self.lookahead]),
+            Cow::Owned(input) => Cow::Owned(String::from(&input[self.cursor..self.lookahead])),
+        }
+    }
+}
+
+/// Problems that prevent the source from being tokenized
+/// every variant carries the (line, column) where the problem started
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenizeError {
+    /// the source ended before a triple quoted string, starting at `start`, was closed
+    UnterminatedTripleQuotedString { start: (usize, usize) },
+    /// the line ended before a single quoted string, starting at `start`, was closed
+    /// only an error in CPython compatible mode, otherwise the quote is an ERRORTOKEN
+    UnterminatedString { start: (usize, usize) },
+    /// the source ended before a bracket, opened at `start`, was closed
+    EofInMultiLineStatement { start: (usize, usize) },
+    /// the line at `start` dedents to a level that matches no enclosing block
+    DedentMismatch { start: (usize, usize) },
+    /// the indentation of the line at `start` mixes tabs and spaces so that it is
+    /// only consistent with the enclosing blocks for some sizes of tab
+    InconsistentTabs { start: (usize, usize) },
+    /// the closing bracket at `start` was never opened
+    UnbalancedCloser { closer: char, start: (usize, usize) },
+    /// the closing bracket at `start` does not match the bracket opened at `opener_start`
+    MismatchedCloser {
+        closer: char,
+        opener: char,
+        opener_start: (usize, usize),
+        start: (usize, usize),
+    },
+    /// the f-string starting at `start` was not closed before the end of its line or the source
+    UnterminatedFString { start: (usize, usize) },
+    /// the char at `start` cannot be part of any token outside of strings and comments
+    /// `suggestion` is a char that it looks like and could be meant instead
+    InvalidCharacter {
+        character: char,
+        suggestion: Option<char>,
+        start: (usize, usize),
+    },
+    /// the char at `start` is not printable and cannot be part of any token outside of strings and comments
+    /// `suggestion` is a char that it looks like and could be meant instead
+    InvalidNonPrintableCharacter {
+        character: char,
+        suggestion: Option<char>,
+        start: (usize, usize),
+    },
+    /// the backslash at `start` is not at the end of its line
+    UnexpectedCharacterAfterContinuation { start: (usize, usize) },
+    /// the decimal integer at `start` has leading zeros, as an octal integer once did
+    LeadingZeros { start: (usize, usize) },
+    /// the number at `start` ends in an underscore, a radix prefix, or an exponent without digits
+    /// only an error in CPython compatible mode, otherwise the number ends before these
+    InvalidNumber { radix: u32, start: (usize, usize) },
+    /// the binary or octal integer at `start` is followed by a decimal digit not valid in it
+    /// only an error in CPython compatible mode, otherwise the digit starts another number
+    InvalidDigit {
+        digit: char,
+        radix: u32,
+        start: (usize, usize),
+    },
+    /// the letters at `start` are directly followed by a quote but are not a string prefix
+    /// in the version of Python being tokenized
+    InvalidStringPrefix {
+        prefix: String,
+        start: (usize, usize),
+    },
+    /// the coding cookie at `start` names an encoding that is not known, or not supported
+    UnknownEncoding {
+        encoding: String,
+        start: (usize, usize),
+    },
+    /// the coding cookie at `start` disagrees with the UTF-8 byte order mark
+    BomEncodingMismatch {
+        encoding: String,
+        start: (usize, usize),
+    },
+    /// the source, from `start`, is not valid in the detected encoding
+    UndecodableSource {
+        encoding: String,
+        start: (usize, usize),
+    },
+    /// the source could not be read past `start`
+    UnreadableSource {
+        message: String,
+        start: (usize, usize),
+    },
+}
+
+impl TokenizeError {
+    /// the (line, column) where the problem started
+    pub fn start(&self) -> (usize, usize) {
+        match self {
+            TokenizeError::UnterminatedTripleQuotedString { start }
+            | TokenizeError::UnterminatedString { start }
+            | TokenizeError::EofInMultiLineStatement { start }
+            | TokenizeError::DedentMismatch { start }
+            | TokenizeError::InconsistentTabs { start }
+            | TokenizeError::UnbalancedCloser { start, .. }
+            | TokenizeError::MismatchedCloser { start, .. }
+            | TokenizeError::UnterminatedFString { start }
+            | TokenizeError::InvalidCharacter { start, .. }
+            | TokenizeError::InvalidNonPrintableCharacter { start, .. }
+            | TokenizeError::UnexpectedCharacterAfterContinuation { start }
+            | TokenizeError::LeadingZeros { start }
+            | TokenizeError::InvalidNumber { start, .. }
+            | TokenizeError::InvalidDigit { start, .. }
+            | TokenizeError::InvalidStringPrefix { start, .. }
+            | TokenizeError::UnknownEncoding { start, .. }
+            | TokenizeError::BomEncodingMismatch { start, .. }
+            | TokenizeError::UndecodableSource { start, .. }
+            | TokenizeError::UnreadableSource { start, .. } => *start,
+        }
+    }
+}
+
+impl fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            TokenizeError::UnterminatedTripleQuotedString { .. } => {
+                String::from("EOF in multi-line string")
+            }
+            TokenizeError::UnterminatedString { .. } => String::from("unterminated string literal"),
+            TokenizeError::EofInMultiLineStatement { .. } => {
+                String::from("EOF in multi-line statement")
+            }
+            TokenizeError::DedentMismatch { .. } => {
+                String::from("dedent does not match any outer indentation level")
+            }
+            TokenizeError::InconsistentTabs { .. } => {
+                String::from("inconsistent use of tabs and spaces in indentation")
+            }
+            TokenizeError::UnbalancedCloser { closer, .. } => format!("unmatched '{}'", closer),
+            TokenizeError::MismatchedCloser {
+                closer,
+                opener,
+                opener_start,
+                ..
+            } => format!(
+                "closing bracket '{}' does not match opening bracket '{}' on line {}, column {}",
+                closer, opener, opener_start.0, opener_start.1
+            ),
+            TokenizeError::UnterminatedFString { .. } => {
+                String::from("unterminated f-string literal")
+            }
+            TokenizeError::InvalidCharacter {
+                character,
+                suggestion,
+                ..
+            } => format!(
+                "invalid character '{}' (U+{:04X}){}",
+                character,
+                *character as u32,
+                did_you_mean(suggestion)
+            ),
+            TokenizeError::InvalidNonPrintableCharacter {
+                character,
+                suggestion,
+                ..
+            } => format!(
+                "invalid non-printable character U+{:04X}{}",
+                *character as u32,
+                did_you_mean(suggestion)
+            ),
+            TokenizeError::UnexpectedCharacterAfterContinuation { .. } => {
+                String::from("unexpected character after line continuation character")
+            }
+            TokenizeError::LeadingZeros { .. } => String::from(
+                "leading zeros in decimal integer literals are not permitted; \
+                use an 0o prefix for octal integers",
+            ),
+            TokenizeError::InvalidNumber { radix, .. } => {
+                format!("invalid {} literal", radix_name(*radix))
+            }
+            TokenizeError::InvalidDigit { digit, radix, .. } => {
+                format!(
+                    "invalid digit '{}' in {} literal",
+                    digit,
+                    radix_name(*radix)
+                )
+            }
+            TokenizeError::InvalidStringPrefix { prefix, .. } => {
+                format!("invalid string prefix '{}'", prefix)
+            }
+            TokenizeError::UnknownEncoding { encoding, .. } => {
+                format!("unknown encoding: {}", encoding)
+            }
+            TokenizeError::BomEncodingMismatch { encoding, .. } => {
+                format!("encoding problem: {} with BOM", encoding)
+            }
+            TokenizeError::UndecodableSource { encoding, .. } => {
+                format!("source cannot be decoded as {}", encoding)
+            }
+            TokenizeError::UnreadableSource { message, .. } => {
+                format!("source cannot be read: {}", message)
+            }
+        };
+        let (line, column) = self.start();
+        write!(f, "{} (line {}, column {})", message, line, column)
+    }
+}
+
+impl Error for TokenizeError {}
+
+fn radix_name(radix: u32) -> &'static str {
+    match radix {
+        2 => "binary",
+        8 => "octal",
+        16 => "hexadecimal",
+        _ => "decimal",
+    }
+}
+
+fn did_you_mean(suggestion: &Option<char>) -> String {
+    match suggestion {
+        Some(suggestion) => format!(", did you mean '{}'?", suggestion),
+        None => String::new(),
+    }
+}
+
+/// The char that c is most likely mistaken for, when c cannot be part of any token
+/// these are the chars that word processors and other keyboard layouts substitute for ascii
+fn confusable(c: char) -> Option<char> {
+    match c {
+        // full-width forms of every ascii char
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0),
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' | '\u{00B4}' => Some('\''),
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' | '\u{00AB}'
+        | '\u{00BB}' => Some('"'),
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => Some('-'),
+        '\u{00D7}' => Some('*'),
+        '\u{00F7}' | '\u{2044}' | '\u{2215}' => Some('/'),
+        '\u{037E}' => Some(';'),
+        '\u{3001}' => Some(','),
+        '\u{3002}' => Some('.'),
+        '\u{3010}' => Some('['),
+        '\u{3011}' => Some(']'),
+        '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}' => Some(' '),
+        _ => None,
+    }
+}
+
+/// The problem with finding c where no token can start with it
+fn invalid_character(c: char, start: (usize, usize)) -> TokenizeError {
+    let suggestion = confusable(c);
+    if c == '\\' {
+        TokenizeError::UnexpectedCharacterAfterContinuation { start }
+    } else if c != ' ' && (c.is_other() || c.is_separator()) {
+        // CPython counts every char in these categories but the space as non-printable
+        TokenizeError::InvalidNonPrintableCharacter {
+            character: c,
+            suggestion,
+            start,
+        }
+    } else {
+        TokenizeError::InvalidCharacter {
+            character: c,
+            suggestion,
+            start,
+        }
+    }
+}
+
+/// Settings that change how source is tokenized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenizerOptions {
+    /// the (major, minor) version of Python whose lexical rules are followed
+    pub python_version: (u8, u8),
+    /// tokenize "<>" as NOTEQUAL, as after `from __future__ import barry_as_FLUFL`
+    pub barry_as_flufl: bool,
+    /// reproduce the token stream of the tokenize module of CPython 3.12
+    /// instead of rye's richer tokens, as described in DIFFERENCES.md
+    pub cpython_compat: bool,
+    /// keep tokenizing the whole source after finding problems with it
+    /// problems are then recorded in `TokenStream::diagnostics` instead of being returned
+    pub recover: bool,
+    /// produce WHITESPACE and CONTINUATION tokens for the text between other tokens
+    /// so that the contents of every token joined together are the whole source
+    /// in CPython compatible mode the second brace of a doubled brace in an f-string is still left out
+    pub trivia: bool,
+    /// tokenize source as it is typed at an interactive prompt, as CPython's single_input mode does
+    /// a line with nothing at all on it ends every open block and is itself a NEWLINE
+    pub single_input: bool,
+    /// produce TYPE_COMMENT and TYPE_IGNORE tokens for comments starting `# type:`,
+    /// as `ast.parse(type_comments=True)` does, instead of COMMENT tokens
+    pub type_comments: bool,
+}
+
+impl TokenizerOptions {
+    /// the newest version of Python whose lexical rules are known
+    pub const LATEST_VERSION: (u8, u8) = (3, 14);
+
+    fn since(&self, version: (u8, u8)) -> bool {
+        self.python_version >= version
+    }
+}
+
+impl Default for TokenizerOptions {
+    fn default() -> Self {
+        TokenizerOptions {
+            python_version: TokenizerOptions::LATEST_VERSION,
+            barry_as_flufl: false,
+            cpython_compat: false,
+            recover: false,
+            trivia: false,
+            single_input: false,
+            type_comments: false,
+        }
+    }
+}
+
+/// Measure an indent with tabs to the next multiple of 8 columns, then as 1 column
+/// formfeeds may be interspersed but are not counted
+pub(crate) fn indent_widths(indent: &str) -> (usize, usize) {
+    let mut spaces = 0;
+    let mut alt_spaces = 0;
+    for c in indent.chars() {
+        if c == ' ' {
+            spaces += 1;
+            alt_spaces += 1;
+        } else if c == '\t' {
+            // round up to the next multiple of 8 spaces
+            spaces += 8 - (spaces % 8);
+            alt_spaces += 1;
+        };
+    }
+    (spaces, alt_spaces)
+}
+
+/// Everything the tokenizer carries from one logical line to the next
+/// tokenizing can resume at the start of any logical line from just this
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LineState {
+    // the widths of every open indent, as returned by indent_widths
+    pub(crate) indents: Vec<(usize, usize)>,
+    // how many indents were open when the enclosing `async def` started, if there is one
+    pub(crate) async_def_indents: Option<usize>,
+}
+
+/// How an f-string or t-string was opened, which decides how its literal text ends
+#[derive(Debug, Clone, Copy)]
+struct FStringQuote {
+    quote: char,
+    triple: bool,
+    raw: bool,
+    // a t-string is tokenized as an f-string is, but into its own token types
+    template: bool,
+    start: (usize, usize),
+    // how many brackets were open outside of the f-string
+    depth: usize,
+}
+
+impl FStringQuote {
+    fn middle_type(&self) -> TokenType {
+        match self.template {
+            true => TokenType::TSTRING_MIDDLE,
+            false => TokenType::FSTRING_MIDDLE,
+        }
     }
 
-    fn peeked_string(&self) -> String {
-        return self.input[self.cursor..self.lookahead].iter().collect();
+    fn end_type(&self) -> TokenType {
+        match self.template {
+            true => TokenType::TSTRING_END,
+            false => TokenType::FSTRING_END,
+        }
     }
 }
 
-pub struct TokenStream {
-    source: RawSource,
+/// Parts of the source that are tokenized by different rules than plain Python
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    /// the literal text of an f-string
+    FString(FStringQuote),
+    /// an expression in braces within an f-string
+    /// depth is the bracket nesting level just inside the opening brace
+    ReplacementField { depth: usize },
+    /// the format spec following a colon in a replacement field
+    FormatSpec,
+}
+
+pub struct TokenStream<'src> {
+    source: RawSource<'src>,
+    options: TokenizerOptions,
     within_statement: bool,
-    parenthesis_level: usize,
-    bracket_level: usize,
-    brace_level: usize,
-    indents_seen: Vec<usize>,
-    tokens: VecDeque<Token>,
+    // every bracket still open, innermost last, with where it was opened
+    brackets: Vec<(char, (usize, usize))>,
+    // every indent is measured with tabs to the next multiple of 8 columns, then as 1 column
+    indents_seen: Vec<(usize, usize)>,
+    // the innermost mode is last; plain Python when empty
+    modes: Vec<Mode>,
+    // how many indents were open when the enclosing `async def` started
+    // only tracked for versions where async and await are not always keywords
+    async_def_indents: Option<usize>,
+    // whether the `async def` statement itself has not yet ended
+    async_def_header: bool,
+    tokens: VecDeque<TokenRef<'src>>,
+    // problems found while recovering
+    diagnostics: Vec<TokenizeError>,
     ended: bool,
 }
 
-impl TokenStream {
-    pub fn new(input: &str) -> TokenStream {
+impl<'src> TokenStream<'src> {
+    pub fn new(input: &'src str) -> TokenStream<'src> {
+        TokenStream::with_options(input, TokenizerOptions::default())
+    }
+
+    /// Tokenize source following the rules chosen by options
+    pub fn with_options(input: &'src str, options: TokenizerOptions) -> TokenStream<'src> {
+        TokenStream::from_source(Cow::Borrowed(input), options)
+    }
+
+    fn from_source(input: Cow<'src, str>, options: TokenizerOptions) -> TokenStream<'src> {
+        TokenStream::from_raw(RawSource::new(input), options)
+    }
+
+    fn from_raw(source: RawSource<'src>, options: TokenizerOptions) -> TokenStream<'src> {
         TokenStream {
-            source: RawSource::new(input),
+            source,
+            options,
             within_statement: false,
-            parenthesis_level: 0,
-            bracket_level: 0,
-            brace_level: 0,
-            indents_seen: vec![0],
+            brackets: Vec::new(),
+            indents_seen: vec![(0, 0)],
+            modes: Vec::new(),
+            async_def_indents: None,
+            async_def_header: false,
             tokens: VecDeque::new(),
+            diagnostics: Vec::new(),
             ended: false,
         }
     }
 
+    /// Tokenize the rest of the source from the start of the logical line at the byte index
+    pub(crate) fn resume(
+        input: &'src str,
+        options: TokenizerOptions,
+        index: usize,
+        state: LineState,
+    ) -> TokenStream<'src> {
+        let mut stream = TokenStream::from_source(Cow::Borrowed(input), options);
+        stream.source.skip_to(index);
+        stream.indents_seen = state.indents;
+        stream.async_def_indents = state.async_def_indents;
+        stream
+    }
+
+    /// The state carried into the next logical line
+    /// only meaningful when no statement is in progress, such as just after a NEWLINE
+    pub(crate) fn line_state(&self) -> LineState {
+        LineState {
+            indents: self.indents_seen.clone(),
+            async_def_indents: self
+                .async_def_indents
+                .filter(|indents| self.indents_seen.len() > *indents),
+        }
+    }
+
+    /// Tokenize Python source that has not yet been decoded
+    /// the encoding is detected from a byte order mark or coding cookie, as described in PEP 263,
+    /// and is always given as the contents of a leading ENCODING token
+    pub fn from_bytes(input: &'src [u8]) -> Result<TokenStream<'src>, TokenizeError> {
+        TokenStream::from_bytes_with_options(input, TokenizerOptions::default())
+    }
+
+    /// Tokenize Python source that has not yet been decoded, following the rules chosen by options
+    pub fn from_bytes_with_options(
+        input: &'src [u8],
+        options: TokenizerOptions,
+    ) -> Result<TokenStream<'src>, TokenizeError> {
+        let (encoding, decoded) = encoding::decode_source(input)?;
+        let mut stream = TokenStream::from_source(decoded, options);
+        stream.add_token(
+            TokenType::ENCODING,
+            TokenType::ENCODING,
+            Cow::Owned(encoding),
+            0..0,
+            0..0,
+            (0, 0),
+            (0, 0),
+        );
+        Ok(stream)
+    }
+
+    /// Tokenize UTF-8 source read one line at a time, as CPython's `tokenize.generate_tokens` does
+    /// only the source not yet tokenized is held in memory, so it may be far larger than memory
+    /// or a pipe such as `io::stdin().lock()` that is still being written
+    /// the source is always read as UTF-8, so a coding cookie naming another encoding is ignored;
+    /// such source is tokenized by `from_bytes`
+    /// problems reading the source are always returned as errors, even when recovering
+    pub fn from_reader<R: BufRead + 'src>(reader: R) -> TokenStream<'src> {
+        TokenStream::from_reader_with_options(reader, TokenizerOptions::default())
+    }
+
+    /// Tokenize UTF-8 source read one line at a time, following the rules chosen by options
+    pub fn from_reader_with_options<R: BufRead + 'src>(
+        reader: R,
+        options: TokenizerOptions,
+    ) -> TokenStream<'src> {
+        TokenStream::from_raw(RawSource::from_reader(Box::new(reader)), options)
+    }
+
+    /// Report a problem with the source
+    /// when recovering the problem is only recorded, so the caller should carry on tokenizing
+    fn report(&mut self, error: TokenizeError) -> Result<(), TokenizeError> {
+        if self.options.recover {
+            self.diagnostics.push(error);
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn add_token(
         &mut self,
         token_type: TokenType,
         exact_token_type: TokenType,
-        token_contents: String,
-        col_start: usize,
-        col_end: usize,
+        token_contents: Cow<'src, str>,
+        cols: Range<usize>,
+        bytes: Range<usize>,
+        start: (usize, usize),
+        end: (usize, usize),
     ) {
-        self.tokens.push_back(Token {
+        self.tokens.push_back(TokenRef {
             token_type,
             exact_token_type,
             token_contents,
-            col_start,
-            col_end,
+            col_start: cols.start,
+            col_end: cols.end,
+            byte_start: bytes.start,
+            byte_end: bytes.end,
+            start,
+            end,
         });
     }
 
+    /// add a token of no size at the current cursor
+    fn add_empty_token(&mut self, token_type: TokenType) {
+        let index = self.source.committed_index();
+        let byte_index = self.source.committed_byte_index();
+        let position = self.source.position(index);
+        self.add_token(
+            token_type,
+            token_type,
+            Cow::Borrowed(""),
+            index..index,
+            byte_index..byte_index,
+            position,
+            position,
+        );
+    }
+
+    /// add a token ending the last line of the input where it has no newline
+    /// CPython counts the newline it adds to the source, so the token ends one column later
+    fn add_missing_newline_token(&mut self, token_type: TokenType) {
+        let index = self.source.committed_index();
+        let byte_index = self.source.committed_byte_index();
+        let (line, column) = self.source.position(index);
+        self.add_token(
+            token_type,
+            token_type,
+            Cow::Borrowed(""),
+            index..index + 1,
+            byte_index..byte_index,
+            (line, column),
+            (line, column + 1),
+        );
+    }
+
+    /// add a token of no size after the end of the input
+    /// the token's byte range is empty at the end of the input, so it can still slice the source
+    fn add_eof_token(&mut self, token_type: TokenType) {
+        let index = self.source.committed_index() + 1;
+        let byte_index = self.source.committed_byte_index();
+        let position = self.source.eof_position();
+        self.add_token(
+            token_type,
+            token_type,
+            Cow::Borrowed(""),
+            index..index,
+            byte_index..byte_index,
+            position,
+            position,
+        );
+    }
+
+    /// Commit text that does not otherwise produce a token
+    /// this becomes a token of its own when keeping trivia
+    fn commit_trivia(&mut self, token_type: TokenType) {
+        if self.options.trivia && self.source.peeked_index() > self.source.committed_index() {
+            self.commit_to_token(token_type);
+        } else {
+            self.source.commit();
+        };
+    }
+
     fn commit_to_token(&mut self, token_type: TokenType) {
         self.commit_to_exact_token(token_type, token_type)
     }
 
     fn commit_to_exact_token(&mut self, token_type: TokenType, exact_token_type: TokenType) {
+        let token_contents = self.source.peeked_str();
+        let col_start = self.source.committed_index();
+        let byte_start = self.source.committed_byte_index();
+        let start = self.source.position(col_start);
+        self.source.commit();
+        let col_end = self.source.committed_index();
+        let byte_end = self.source.committed_byte_index();
+        let mut end = self.source.end_position(col_start, col_end);
+        if self.options.cpython_compat
+            && token_contents.ends_with('\n')
+            && !matches!(token_type, TokenType::NEWLINE | TokenType::NL)
+        {
+            // CPython ends any other token that ends in a newline at the start of the next line
+            end = (end.0 + 1, 0);
+        };
         self.add_token(
             token_type,
             exact_token_type,
-            self.source.peeked_string(),
-            self.source.committed_index(),
-            self.source.peeked_index(),
+            token_contents,
+            col_start..col_end,
+            byte_start..byte_end,
+            start,
+            end,
         );
-        self.source.commit();
         if token_type == TokenType::NEWLINE {
             self.within_statement = false;
-        } else if !(token_type == TokenType::NL || token_type == TokenType::COMMENT) {
+            self.async_def_header = false;
+        } else if !matches!(
+            token_type,
+            TokenType::NL
+                | TokenType::COMMENT
+                | TokenType::TYPE_COMMENT
+                | TokenType::TYPE_IGNORE
+                | TokenType::WHITESPACE
+                | TokenType::CONTINUATION
+        ) {
             self.within_statement = true;
         };
         // else NL and COMMENT don't either start or end a statement
     }
 
+    /// Close the innermost open bracket with closer, found at start
+    /// in CPython compatible mode brackets are only counted, so any closer closes any opener
+    /// and a closer with nothing open is left for the parser to reject
+    /// the brace opening a replacement field is only ever closed by the field ending
+    fn close_bracket(&mut self, closer: char, start: (usize, usize)) -> Result<(), TokenizeError> {
+        let compat = self.options.cpython_compat;
+        let field_depth = match self.modes.last() {
+            Some(Mode::ReplacementField { depth }) => *depth,
+            _ => 0,
+        };
+        let within_field = self.brackets.len() > field_depth;
+        match self.brackets.last().copied() {
+            Some((opener, opener_start))
+                if !within_field
+                    || !compat
+                        && !matches!((opener, closer), ('(', ')') | ('[', ']') | ('{', '}')) =>
+            {
+                if within_field {
+                    self.brackets.pop();
+                };
+                Err(TokenizeError::MismatchedCloser {
+                    closer,
+                    opener,
+                    opener_start,
+                    start,
+                })
+            }
+            Some(_) => {
+                self.brackets.pop();
+                Ok(())
+            }
+            None if compat => Ok(()),
+            None => Err(TokenizeError::UnbalancedCloser { closer, start }),
+        }
+    }
+
     /// Attempt to consume the longest valid op token from the source
     /// advance the cursor if any op is found
-    fn consume_next_op_token(&mut self) -> bool {
+    /// a closing bracket that does not close the innermost open bracket is consumed as an error
+    fn consume_next_op_token(&mut self) -> Result<bool, TokenizeError> {
+        let start = self.source.position(self.source.committed_index());
+        let mut opened = None;
+        let mut closed = None;
+        let walrus = self.options.since((3, 8));
+        let matmul = self.options.since((3, 5));
+        let barry_as_flufl = self.options.barry_as_flufl;
+        let compat = self.options.cpython_compat;
         let exact_token_type: TokenType;
         match self.source.peek(3) {
             ['*', '*', '='] => exact_token_type = TokenType::DOUBLESTAREQUAL,
@@ -174,7 +989,7 @@ impl TokenStream {
                 exact_token_type = TokenType::SLASHEQUAL;
                 self.source.hide(1);
             }
-            [':', '=', ..] => {
+            [':', '=', ..] if walrus => {
                 exact_token_type = TokenType::COLONEQUAL;
                 self.source.hide(1);
             }
@@ -186,7 +1001,12 @@ impl TokenStream {
                 exact_token_type = TokenType::LESSEQUAL;
                 self.source.hide(1);
             }
-            ['<', '>', ..] => {
+            ['<', '>', ..] if compat => {
+                // CPython's tokenize module reads this as one op with no exact type of its own
+                exact_token_type = TokenType::OP;
+                self.source.hide(1);
+            }
+            ['<', '>', ..] if barry_as_flufl => {
                 exact_token_type = TokenType::NOTEQUAL;
                 self.source.hide(1);
             }
@@ -202,7 +1022,7 @@ impl TokenStream {
                 exact_token_type = TokenType::RIGHTSHIFT;
                 self.source.hide(1);
             }
-            ['@', '=', ..] => {
+            ['@', '=', ..] if matmul => {
                 exact_token_type = TokenType::ATEQUAL;
                 self.source.hide(1);
             }
@@ -214,6 +1034,15 @@ impl TokenStream {
                 exact_token_type = TokenType::VBAREQUAL;
                 self.source.hide(1);
             }
+            ['!', ..] if compat => {
+                exact_token_type = TokenType::EXCLAMATION;
+                self.source.hide(2);
+            }
+            ['$' | '?' | '`', ..] if compat => {
+                // CPython's tokenize module leaves these for the parser to reject
+                exact_token_type = TokenType::OP;
+                self.source.hide(2);
+            }
             ['%', ..] => {
                 exact_token_type = TokenType::PERCENT;
                 self.source.hide(2);
@@ -225,12 +1054,12 @@ impl TokenStream {
             ['(', ..] => {
                 exact_token_type = TokenType::LPAR;
                 self.source.hide(2);
-                self.parenthesis_level += 1;
+                opened = Some('(');
             }
             [')', ..] => {
                 exact_token_type = TokenType::RPAR;
                 self.source.hide(2);
-                self.parenthesis_level = self.parenthesis_level.saturating_sub(1);
+                closed = Some(')');
             }
             ['*', ..] => {
                 exact_token_type = TokenType::STAR;
@@ -283,12 +1112,12 @@ impl TokenStream {
             ['[', ..] => {
                 exact_token_type = TokenType::LSQB;
                 self.source.hide(2);
-                self.bracket_level += 1;
+                opened = Some('[');
             }
             [']', ..] => {
                 exact_token_type = TokenType::RSQB;
                 self.source.hide(2);
-                self.bracket_level = self.bracket_level.saturating_sub(1);
+                closed = Some(']');
             }
             ['^', ..] => {
                 exact_token_type = TokenType::CIRCUMFLEX;
@@ -297,7 +1126,7 @@ impl TokenStream {
             ['{', ..] => {
                 exact_token_type = TokenType::LBRACE;
                 self.source.hide(2);
-                self.brace_level += 1;
+                opened = Some('{');
             }
             ['|', ..] => {
                 exact_token_type = TokenType::VBAR;
@@ -306,7 +1135,7 @@ impl TokenStream {
             ['}', ..] => {
                 exact_token_type = TokenType::RBRACE;
                 self.source.hide(2);
-                self.brace_level = self.brace_level.saturating_sub(1);
+                closed = Some('}');
             }
             ['~', ..] => {
                 exact_token_type = TokenType::TILDE;
@@ -314,38 +1143,41 @@ impl TokenStream {
             }
             _ => {
                 self.source.revert();
-                return false;
+                return Ok(false);
             }
         }
+        if let Some(closer) = closed {
+            if let Err(e) = self.close_bracket(closer, start) {
+                // the closer is still an op when recovering
+                self.report(e)?;
+            };
+        };
+        if let Some(opener) = opened {
+            self.brackets.push((opener, start));
+        };
         self.commit_to_exact_token(TokenType::OP, exact_token_type);
-        true
+        Ok(true)
     }
 
+    /// Whether c may start a name, following PEP 3131
+    /// this is XID_Start, which includes Other_ID_Start, and the underscore
     fn is_start_of_name(c: &char) -> bool {
-        // XXX: also chars with Other_ID_Start property
-        c.is_letter_uppercase()
-            || c.is_letter_lowercase()
-            || c.is_letter_titlecase()
-            || c.is_letter_modifier()
-            || c.is_letter_other()
-            || c.is_number_letter()
-            || *c == '_'
+        *c == '_' || unicode_ident::is_xid_start(*c)
     }
 
+    /// Whether c may continue a name, following PEP 3131
+    /// this is XID_Continue, which includes Other_ID_Continue
     fn is_part_of_name(c: &char) -> bool {
-        // XXX: also chars with Other_ID_Continue
-        Self::is_start_of_name(c)
-            || c.is_mark_nonspacing()
-            || c.is_mark_spacing_combining()
-            || c.is_number_decimal_digit()
-            || c.is_punctuation_connector()
+        unicode_ident::is_xid_continue(*c)
     }
 
     /// Attempt to consume the longest valid name token from the source
     /// advance the cursor if any name is found
+    /// in CPython compatible mode every non-ASCII char is part of a name, as the parser checks them
     fn consume_next_name_token(&mut self) -> bool {
+        let compat = self.options.cpython_compat;
         if let [next] = self.source.peek(1) {
-            if !Self::is_start_of_name(next) {
+            if !Self::is_start_of_name(next) && (!compat || next.is_ascii()) {
                 self.source.hide(1);
                 return false;
             }
@@ -356,7 +1188,7 @@ impl TokenStream {
 
         loop {
             if let [next] = self.source.peek(1) {
-                if !Self::is_part_of_name(next) {
+                if !Self::is_part_of_name(next) && (!compat || next.is_ascii()) {
                     self.source.hide(1);
                     break;
                 }
@@ -366,10 +1198,52 @@ impl TokenStream {
             };
         }
 
-        self.commit_to_token(TokenType::NAME);
+        let token_type = self.async_keyword_type();
+        self.commit_to_token(token_type);
         true
     }
 
+    /// The type of the peeked name, which is only not a NAME for async and await
+    /// in the versions where they are keywords only in and around `async def`
+    fn async_keyword_type(&mut self) -> TokenType {
+        if !self.options.since((3, 5)) || self.options.since((3, 7)) {
+            return TokenType::NAME;
+        };
+        let in_async_def = match self.async_def_indents {
+            Some(indents) => self.async_def_header || self.indents_seen.len() > indents,
+            None => false,
+        };
+        if !in_async_def {
+            self.async_def_indents = None;
+        };
+        match &*self.source.peeked_str() {
+            "async" if in_async_def => TokenType::ASYNC,
+            "await" if in_async_def => TokenType::AWAIT,
+            "async" if self.def_follows() => {
+                self.async_def_indents = Some(self.indents_seen.len());
+                self.async_def_header = true;
+                TokenType::ASYNC
+            }
+            _ => TokenType::NAME,
+        }
+    }
+
+    /// Check, without consuming it, whether the next name after the peeked one is `def`
+    fn def_follows(&mut self) -> bool {
+        let mut peeked = 0;
+        let mut name = String::new();
+        loop {
+            peeked += 1;
+            match self.source.peek(1) {
+                [' ' | '\t' | '\u{000C}'] if name.is_empty() => (),
+                [next] if Self::is_part_of_name(next) => name.push(*next),
+                _ => break,
+            };
+        }
+        self.source.hide(peeked);
+        name == "def"
+    }
+
     fn is_bin_digit(c: &char) -> bool {
         *c == '0' || *c == '1'
     }
@@ -408,10 +1282,11 @@ impl TokenStream {
     /// for non-decimal integers this means "0[bBoOxX][0-9a-fA-F]" has already been matched
     /// for decimal integers just the first digit has already been matched
     fn find_end_of_integer(&mut self, valid_digit: fn(&char) -> bool) {
+        let underscores = self.options.since((3, 6));
         let mut last_under = false;
         loop {
             match self.source.peek(1) {
-                ['_'] => {
+                ['_'] if underscores => {
                     if last_under {
                         // neither of these "_" are part of a number
                         self.source.hide(2);
@@ -459,12 +1334,33 @@ impl TokenStream {
 
     /// Attempt to consume the longest valid number token from the source
     /// advance the cursor if any name is found
-    fn consume_next_number_token(&mut self) -> bool {
+    fn consume_next_number_token(&mut self) -> Result<bool, TokenizeError> {
+        let underscores = self.options.since((3, 6));
         let number_type: TokenType;
 
         match self.source.peek(1) {
             ['0'] => {
                 match self.source.peek(2) {
+                    [radix @ ('b' | 'B' | 'o' | 'O' | 'x' | 'X'), '_'] if underscores => {
+                        // an underscore may also separate the radix from the first digit
+                        let (radix_type, valid_digit): (TokenType, fn(&char) -> bool) =
+                            match radix.to_ascii_lowercase() {
+                                'b' => (TokenType::BININT, Self::is_bin_digit),
+                                'o' => (TokenType::OCTINT, Self::is_oct_digit),
+                                _ => (TokenType::HEXINT, Self::is_hex_digit),
+                            };
+                        match self.source.peek(1) {
+                            [next] if valid_digit(next) => {
+                                number_type = radix_type;
+                                self.find_end_of_integer(valid_digit);
+                            }
+                            _ => {
+                                // found decimal number zero spelled with one 0
+                                number_type = TokenType::INTEGER;
+                                self.source.hide(3);
+                            }
+                        };
+                    }
                     ['b' | 'B', next] if Self::is_bin_digit(next) => {
                         number_type = TokenType::BININT;
                         self.find_end_of_integer(Self::is_bin_digit);
@@ -477,7 +1373,7 @@ impl TokenStream {
                         number_type = TokenType::HEXINT;
                         self.find_end_of_integer(Self::is_hex_digit);
                     }
-                    [next, ..] if Self::is_dec_digit(next) || *next == '_' => {
+                    [next, ..] if Self::is_dec_digit(next) || *next == '_' && underscores => {
                         // at this point the longest possible integer token is a zero as only zero
                         // can have leading 0s
                         let last_zero: usize;
@@ -492,9 +1388,9 @@ impl TokenStream {
                             self.find_end_of_integer(|c| *c == '0');
                             last_zero = self.source.peeked_index();
                         } else {
-                            // put unchecked char back
-                            self.source.hide(1);
-                            last_zero = self.source.peeked_index() - 1;
+                            // put both unchecked chars back
+                            self.source.hide(2);
+                            last_zero = self.source.peeked_index();
                         };
                         match self.source.peek(1) {
                             ['.'] => {
@@ -516,11 +1412,13 @@ impl TokenStream {
                                     number_type = TokenType::INTEGER;
                                 };
                             }
-                            [next] if Self::is_dec_digit(next) || *next == '_' => {
+                            [next] if Self::is_dec_digit(next) || *next == '_' && underscores => {
                                 // 0 digits are certain to be part of one token but non-0 digits
                                 // are only part of the same token if it ends up being a float or
                                 // imaginary
                                 self.find_end_of_integer(Self::is_dec_digit);
+                                // the exponent arm cannot be a guard as it advances the source
+                                #[allow(clippy::collapsible_match)]
                                 match self.source.peek(1) {
                                     ['.'] => {
                                         // found fraction with integer part non-zero but leading 0s
@@ -537,6 +1435,10 @@ impl TokenStream {
                                             // found exponent with base part non-zero but with
                                             // leading 0s
                                             number_type = TokenType::FLOAT;
+                                        } else if self.options.cpython_compat {
+                                            // the leading 0s and the digits after them are
+                                            // still one number, as below
+                                            number_type = TokenType::INTEGER;
                                         } else {
                                             // found decimal number zero spelled with multiple 0s
                                             number_type = TokenType::INTEGER;
@@ -551,6 +1453,16 @@ impl TokenStream {
                                         // whole number tokens have different lexing rules around 0
                                         number_type = TokenType::IMAGINARY;
                                     }
+                                    _ if self.options.cpython_compat => {
+                                        // CPython's tokenize module reads the leading 0s and the
+                                        // digits after them as one number for the parser to reject
+                                        number_type = TokenType::INTEGER;
+                                        self.source.hide(1);
+                                        if self.source.peeked_str().ends_with('_') {
+                                            // no digit follows this underscore
+                                            self.source.hide(1);
+                                        };
+                                    }
                                     _ => {
                                         // found decimal number zero spelled with multiple 0s
                                         number_type = TokenType::INTEGER;
@@ -638,14 +1550,14 @@ impl TokenStream {
                     _ => {
                         // just a dot
                         self.source.revert();
-                        return false;
+                        return Ok(false);
                     }
                 }
             }
             _ => {
                 // no number here
                 self.source.revert();
-                return false;
+                return Ok(false);
             }
         };
 
@@ -661,60 +1573,143 @@ impl TokenStream {
             exact_token_type = number_type;
         };
 
+        if self.options.cpython_compat {
+            // CPython's tokenize module refuses numbers that rye would end early
+            let radix = match exact_token_type {
+                TokenType::BININT => 2,
+                TokenType::OCTINT => 8,
+                TokenType::HEXINT => 16,
+                _ => 10,
+            };
+            let imaginary = exact_token_type == TokenType::IMAGINARY;
+            let zero = self.source.peeked_str() == "0";
+            let exponent = self.source.peeked_str().contains(['e', 'E']);
+            let start = self.source.position(self.source.committed_index());
+            let error = match self.source.peek(2) {
+                ['_', ..] if underscores && !imaginary => {
+                    Some(TokenizeError::InvalidNumber { radix, start })
+                }
+                [prefix @ ('b' | 'B' | 'o' | 'O' | 'x' | 'X'), ..] if zero => {
+                    Some(TokenizeError::InvalidNumber {
+                        radix: match prefix.to_ascii_lowercase() {
+                            'b' => 2,
+                            'o' => 8,
+                            _ => 16,
+                        },
+                        start,
+                    })
+                }
+                [digit, ..] if radix < 10 && Self::is_dec_digit(digit) => {
+                    Some(TokenizeError::InvalidDigit {
+                        digit: *digit,
+                        radix,
+                        start,
+                    })
+                }
+                ['e' | 'E', '+' | '-'] if radix == 10 && !imaginary && !exponent => {
+                    Some(TokenizeError::InvalidNumber { radix, start })
+                }
+                _ => None,
+            };
+            self.source.hide(2);
+            if let Some(error) = error {
+                self.report(error)?;
+            };
+            self.commit_to_token(TokenType::NUMBER);
+            return Ok(true);
+        };
+        if exact_token_type == TokenType::INTEGER && self.source.peeked_str().starts_with('0') {
+            // only zero can be spelled with leading zeros, which CPython refuses to read as octal
+            let digits_follow = match self.source.peek(2) {
+                [next, ..] if Self::is_dec_digit(next) => true,
+                ['_', next] => underscores && Self::is_dec_digit(next),
+                _ => false,
+            };
+            self.source.hide(2);
+            if digits_follow {
+                self.report(TokenizeError::LeadingZeros {
+                    start: self.source.position(self.source.committed_index()),
+                })?;
+            };
+        };
         self.commit_to_exact_token(TokenType::NUMBER, exact_token_type);
-        true
+        Ok(true)
     }
 
     /// Attempt to consume a newline
     /// advance the cursor if a newline is detected
+    /// any of \n, \r\n, or \r end a line
     fn consume_next_newline(&mut self) -> Option<bool> {
-        return match self.source.peek(2) {
-            ['\\', '\n'] => {
-                // no tokens produced when newline escaped
-                self.source.commit();
-                Some(false)
+        match self.source.peek(3) {
+            ['\\', '\r', '\n'] => {
+                // no tokens produced when newline escaped, unless keeping trivia
+                self.commit_trivia(TokenType::CONTINUATION);
+                return Some(false);
             }
-            ['\n', ..] => {
+            ['\\', '\n' | '\r', ..] => {
+                // no tokens produced when newline escaped, unless keeping trivia
                 self.source.hide(1);
-                if self.within_statement
-                    && self.parenthesis_level == 0
-                    && self.bracket_level == 0
-                    && self.brace_level == 0
-                {
-                    self.commit_to_token(TokenType::NEWLINE);
-                    Some(true)
-                } else {
-                    self.commit_to_token(TokenType::NL);
-                    Some(true)
-                }
+                self.commit_trivia(TokenType::CONTINUATION);
+                return Some(false);
+            }
+            ['\r', '\n', ..] => {
+                self.source.hide(1);
+            }
+            ['\n' | '\r', ..] => {
+                self.source.hide(2);
             }
             _ => {
                 self.source.revert();
-                None
+                return None;
             }
         };
+        if self.within_statement && self.brackets.is_empty() {
+            self.commit_to_token(TokenType::NEWLINE);
+        } else {
+            self.commit_to_token(TokenType::NL);
+        };
+        Some(true)
     }
 
     /// Attempt to consume leading whitespace from a logical line in the source
     /// advance the cursor if any name is found
     /// This must be called first after every NEWLINE but not after other tokens
-    fn consume_next_dent(&mut self) -> Result<bool, String> {
-        let mut spaces: usize = 0;
+    fn consume_next_dent(&mut self) -> Result<bool, TokenizeError> {
+        let line_start = self.source.position(self.source.committed_index());
+        if line_start.1 != 0 {
+            // the indentation of this line was already consumed by an earlier DEDENT or INDENT
+            return Ok(false);
+        };
+        if self.options.single_input {
+            // at an interactive prompt a line with nothing on it ends the statement being typed
+            let empty_line = match self.source.peek(2) {
+                ['\r', '\n'] => true,
+                ['\n' | '\r', ..] => {
+                    self.source.hide(1);
+                    true
+                }
+                _ => false,
+            };
+            if empty_line {
+                while self.indents_seen.len() > 1 {
+                    self.indents_seen.pop();
+                    self.add_empty_token(TokenType::DEDENT);
+                }
+                self.commit_to_token(TokenType::NEWLINE);
+                return Ok(true);
+            };
+            self.source.revert();
+        };
+        let inconsistent_tabs = TokenizeError::InconsistentTabs { start: line_start };
         let mut no_more_source = true;
         while let [next] = self.source.peek(1) {
-            if *next == ' ' {
-                spaces += 1;
-            } else if *next == '\t' {
-                // round up to the next multiple of 8 spaces
-                spaces += 8 - (spaces % 8);
-            } else if *next == '\u{000C}' {
-                // formfeeds don't count toward indentation but may be interspersed
+            if *next == ' ' || *next == '\t' || *next == '\u{000C}' {
                 continue;
-            } else if *next == '\n' || *next == '\\' || *next == '#' {
+            } else if *next == '\n' || *next == '\r' || *next == '\\' || *next == '#' {
                 // there is no code on this line and no tokens are produced from any indent
                 // any indent does not have to line up with any other line and has no significance
                 self.source.hide(1);
-                self.source.commit();
+                self.commit_trivia(TokenType::WHITESPACE);
                 return Ok(false);
             } else {
                 no_more_source = false;
@@ -724,51 +1719,62 @@ impl TokenStream {
         self.source.hide(1);
         if no_more_source {
             // there is no code on this line
-            self.source.commit();
+            let blank = self.source.peeked_index() > self.source.committed_index();
+            self.commit_trivia(TokenType::WHITESPACE);
+            if self.options.cpython_compat && blank {
+                // CPython ends a last line of only whitespace even without a newline
+                self.add_missing_newline_token(TokenType::NL);
+            };
             return Ok(false);
         };
+        // the second width is the same indent if tabs were only one space
+        let (spaces, alt_spaces) = indent_widths(&self.source.peeked_str());
 
+        // both sizes of tab must agree on how this line compares to the current block
         match *self.indents_seen.last().unwrap() {
-            s if s == spaces => {
+            (s, alt) if s == spaces => {
+                if alt != alt_spaces {
+                    self.report(inconsistent_tabs)?;
+                };
                 // this line is the same indentation level as the current block
-                self.source.commit();
+                self.commit_trivia(TokenType::WHITESPACE);
                 Ok(false)
             }
-            s if s < spaces => {
-                self.indents_seen.push(spaces);
+            (s, alt) if s < spaces => {
+                if alt >= alt_spaces {
+                    self.report(inconsistent_tabs)?;
+                };
+                self.indents_seen.push((spaces, alt_spaces));
                 self.commit_to_token(TokenType::INDENT);
                 Ok(true)
             }
             _ => {
                 // DEDENT size must match a previously seen INDENT size
                 // one or more DEDENTs may be produced until such a match is found
-                self.source.commit();
+                self.commit_trivia(TokenType::WHITESPACE);
                 loop {
                     self.indents_seen.pop();
                     match *self.indents_seen.last().unwrap() {
-                        s if s == spaces => {
-                            self.add_token(
-                                TokenType::DEDENT,
-                                TokenType::DEDENT,
-                                String::from(""),
-                                self.source.committed_index(),
-                                self.source.committed_index(),
-                            );
+                        (s, alt) if s == spaces => {
+                            if alt != alt_spaces {
+                                self.report(inconsistent_tabs)?;
+                            };
+                            self.add_empty_token(TokenType::DEDENT);
                             return Ok(true);
                         }
-                        s if s < spaces || self.indents_seen.len() == 1 => {
-                            return Err(String::from(
-                                "dedent does not match any outer indentation level",
-                            ));
+                        (s, _) if s < spaces || self.indents_seen.len() == 1 => {
+                            self.report(TokenizeError::DedentMismatch {
+                                start: self.source.position(self.source.committed_index()),
+                            })?;
+                            // when recovering the line closes the block it was in
+                            // and starts a new block at its own indentation
+                            self.add_empty_token(TokenType::DEDENT);
+                            self.indents_seen.push((spaces, alt_spaces));
+                            self.add_empty_token(TokenType::INDENT);
+                            return Ok(true);
                         }
                         _ => {
-                            self.add_token(
-                                TokenType::DEDENT,
-                                TokenType::DEDENT,
-                                String::from(""),
-                                self.source.committed_index(),
-                                self.source.committed_index(),
-                            );
+                            self.add_empty_token(TokenType::DEDENT);
                         }
                     };
                 }
@@ -788,19 +1794,28 @@ impl TokenStream {
         }
         // everything but the last peek was whitespace
         self.source.hide(1);
-        self.source.commit();
+        self.commit_trivia(TokenType::WHITESPACE);
     }
 
     fn consume_next_comment(&mut self) -> bool {
         if let [next] = self.source.peek(1) {
             if *next == '#' {
                 while let [next] = self.source.peek(1) {
-                    if *next == '\n' {
+                    if *next == '\n' || *next == '\r' {
                         break;
                     };
                 }
                 self.source.hide(1);
-                self.commit_to_token(TokenType::COMMENT);
+                let token_type = match self.options.type_comments {
+                    true => split_type_comment(&self.source.peeked_str())
+                        .map_or(TokenType::COMMENT, |(token_type, _)| token_type),
+                    false => TokenType::COMMENT,
+                };
+                self.commit_to_token(token_type);
+                if self.options.cpython_compat && !self.within_statement && self.source.at_end() {
+                    // CPython ends a line of only a comment even without a newline
+                    self.add_missing_newline_token(TokenType::NL);
+                };
                 return true;
             };
         };
@@ -808,7 +1823,7 @@ impl TokenStream {
         false
     }
 
-    fn find_end_tripple_quote(&mut self, end_match: [char; 3]) -> Result<(), String> {
+    fn find_end_tripple_quote(&mut self, end_match: [char; 3]) -> Result<(), TokenizeError> {
         let mut last_escape = false;
         while let [a, b, c] = self.source.peek(3) {
             if [*a, *b, *c] == end_match && !last_escape {
@@ -823,55 +1838,83 @@ impl TokenStream {
             };
             self.source.hide(2);
         }
-        Err(String::from("EOF in multi-line string"))
+        Err(TokenizeError::UnterminatedTripleQuotedString {
+            start: self.source.position(self.source.committed_index()),
+        })
     }
 
     fn find_end_quote(&mut self, end_match: [char; 1]) -> bool {
         let mut last_escape = false;
+        let mut last_escaped_cr = false;
         while let [a] = self.source.peek(1) {
+            let escaped_cr = last_escape && *a == '\r';
             if [*a] == end_match && !last_escape {
                 return true;
-            } else if [*a] == ['\n'] {
+            } else if *a == '\n' && last_escaped_cr {
+                // second half of an escaped \r\n, the string continues on the next line
+            } else if (*a == '\n' || *a == '\r') && !last_escape {
                 return false;
-            } else if [*a] == ['\\'] && !last_escape {
-                last_escape = true;
-            } else if last_escape {
-                last_escape = false;
             };
+            last_escape = *a == '\\' && !last_escape;
+            last_escaped_cr = escaped_cr;
         }
         false
     }
 
-    fn consume_next_string_token(&mut self) -> Result<bool, String> {
-        let qt: char;
-        match self.source.peek(3) {
-            [q, ..] if q == &'\'' || q == &'"' => {
-                qt = *q;
-                self.source.hide(2);
-            }
-            ['b' | 'B', q, ..] | ['f' | 'F', q, ..] | ['r' | 'R', q, ..] | ['u' | 'U', q, ..]
-                if q == &'\'' || q == &'"' =>
-            {
-                qt = *q;
-                self.source.hide(1);
-            }
-            ['r' | 'R', 'b' | 'B' | 'f' | 'F', q] | ['b' | 'B' | 'f' | 'F', 'r' | 'R', q]
-                if q == &'\'' || q == &'"' =>
-            {
-                qt = *q;
-            }
-            _ => {
+    fn consume_next_string_token(&mut self) -> Result<bool, TokenizeError> {
+        // every letter before the quote is part of the prefix
+        let mut letters = String::new();
+        let qt = loop {
+            match self.source.peek(1) {
+                [q @ ('\'' | '"')] => break *q,
+                [c] if is_prefix_letter(*c) => letters.push(*c),
+                _ => {
+                    self.source.revert();
+                    return Ok(false);
+                }
+            };
+        };
+        let prefix_len = letters.chars().count();
+        let prefix = match StringPrefix::parse(&letters, self.options.python_version) {
+            Some(prefix) => prefix,
+            None => {
+                // CPython's tokenize module leaves these for the parser to reject
+                if !self.options.cpython_compat {
+                    self.report(TokenizeError::InvalidStringPrefix {
+                        prefix: letters,
+                        start: self.source.position(self.source.committed_index()),
+                    })?;
+                };
+                // the letters are only a name, followed by a string without a prefix
                 self.source.revert();
                 return Ok(false);
             }
         };
+        if prefix.template || prefix.format && self.options.since((3, 12)) {
+            self.consume_fstring_start(qt, prefix);
+            return Ok(true);
+        };
         match self.source.peek(2) {
             [a, b] if [*a, *b] == [qt, qt] => {
-                self.find_end_tripple_quote([qt, qt, qt])?;
+                if let Err(e) = self.find_end_tripple_quote([qt, qt, qt]) {
+                    self.report(e)?;
+                    // when recovering only the opening quotes are in error
+                    // everything after them is tokenized as though they were not there
+                    self.source.revert();
+                    self.source.peek(prefix_len);
+                    self.source.peek(3);
+                    self.commit_to_token(TokenType::ERRORTOKEN);
+                    return Ok(true);
+                };
             }
             _ => {
                 self.source.hide(2);
                 if !self.find_end_quote([qt]) {
+                    if self.options.cpython_compat {
+                        self.report(TokenizeError::UnterminatedString {
+                            start: self.source.position(self.source.committed_index()),
+                        })?;
+                    };
                     self.source.revert();
                     self.source.peek(1);
                     self.commit_to_token(TokenType::ERRORTOKEN);
@@ -883,49 +1926,302 @@ impl TokenStream {
         Ok(true)
     }
 
-    fn finalize_stream(&mut self) -> Result<(), String> {
-        if self.parenthesis_level != 0 || self.brace_level != 0 || self.bracket_level != 0 {
-            return Err(String::from("EOF in multi-line statement"));
+    /// Start an f-string or t-string whose prefix and opening quote have been peeked
+    fn consume_fstring_start(&mut self, quote: char, prefix: StringPrefix) {
+        let triple = match self.source.peek(2) {
+            [a, b] => *a == quote && *b == quote,
+            _ => false,
+        };
+        if !triple {
+            self.source.hide(2);
+        };
+        let start = self.source.position(self.source.committed_index());
+        self.commit_to_token(match prefix.template {
+            true => TokenType::TSTRING_START,
+            false => TokenType::FSTRING_START,
+        });
+        self.modes.push(Mode::FString(FStringQuote {
+            quote,
+            triple,
+            raw: prefix.raw,
+            template: prefix.template,
+            start,
+            depth: self.nesting_level(),
+        }));
+    }
+
+    /// the f-string that the innermost mode is part of
+    fn current_fstring(&self) -> FStringQuote {
+        self.modes
+            .iter()
+            .rev()
+            .find_map(|mode| match mode {
+                Mode::FString(fstring) => Some(*fstring),
+                _ => None,
+            })
+            .expect("f-string modes are always within an f-string")
+    }
+
+    fn nesting_level(&self) -> usize {
+        self.brackets.len()
+    }
+
+    /// Peek the literal text of an f-string, stopping before whatever ends it
+    /// within a format spec a closing brace also ends the text and braces are never doubled
+    /// in CPython compatible mode the text also ends between the braces of a doubled brace,
+    /// which is the only time this returns true
+    fn find_end_of_fstring_middle(
+        &mut self,
+        fstring: FStringQuote,
+        format_spec: bool,
+    ) -> Result<bool, TokenizeError> {
+        let unterminated = TokenizeError::UnterminatedFString {
+            start: fstring.start,
+        };
+        let mut named_escape = false;
+        loop {
+            match self.source.peek(1) {
+                [] => {
+                    self.source.hide(1);
+                    return Err(unterminated);
+                }
+                ['}'] if named_escape => named_escape = false,
+                [brace @ ('{' | '}')] => {
+                    let brace = *brace;
+                    if !format_spec {
+                        match self.source.peek(1) {
+                            // a doubled brace is literal text
+                            [next] if *next == brace => {
+                                if self.options.cpython_compat {
+                                    self.source.hide(1);
+                                    return Ok(true);
+                                };
+                                continue;
+                            }
+                            // so is a lone closing brace
+                            _ if brace == '}' => {
+                                self.source.hide(1);
+                                continue;
+                            }
+                            _ => self.source.hide(1),
+                        };
+                    };
+                    self.source.hide(1);
+                    return Ok(false);
+                }
+                ['\\'] => match self.source.peek(1) {
+                    ['N'] if !fstring.raw => match self.source.peek(1) {
+                        ['{'] => named_escape = true,
+                        _ => self.source.hide(1),
+                    },
+                    ['\r'] => {
+                        if let [next] = self.source.peek(1) {
+                            if *next != '\n' {
+                                self.source.hide(1);
+                            };
+                        } else {
+                            self.source.hide(1);
+                        };
+                    }
+                    [next] if *next == fstring.quote || *next == '\\' || *next == '\n' => (),
+                    _ => self.source.hide(1),
+                },
+                ['\n' | '\r'] if !fstring.triple => {
+                    self.source.hide(1);
+                    return Err(unterminated);
+                }
+                [q] if *q == fstring.quote => {
+                    if !fstring.triple {
+                        self.source.hide(1);
+                        return Ok(false);
+                    };
+                    match self.source.peek(2) {
+                        [a, b] if *a == fstring.quote && *b == fstring.quote => {
+                            self.source.hide(3);
+                            return Ok(false);
+                        }
+                        _ => self.source.hide(2),
+                    };
+                }
+                _ => (),
+            };
+        }
+    }
+
+    /// End the innermost f-string where it was found to be unterminated
+    /// any text already peeked is kept and the f-string is closed by an empty FSTRING_END
+    fn abandon_fstring(&mut self) {
+        let fstring = self.current_fstring();
+        if self.source.peeked_index() > self.source.committed_index() {
+            self.commit_to_token(fstring.middle_type());
+        };
+        self.add_empty_token(fstring.end_type());
+        while let Some(mode) = self.modes.pop() {
+            if let Mode::FString(_) = mode {
+                break;
+            };
+        }
+        self.brackets.truncate(fstring.depth);
+    }
+
+    /// Consume the next token from the literal text of an f-string
+    fn consume_next_fstring_token(&mut self, fstring: FStringQuote) -> Result<(), TokenizeError> {
+        let doubled_brace = match self.find_end_of_fstring_middle(fstring, false) {
+            Ok(doubled_brace) => doubled_brace,
+            Err(e) => {
+                self.report(e)?;
+                self.abandon_fstring();
+                return Ok(());
+            }
+        };
+        if self.source.peeked_index() > self.source.committed_index() {
+            self.commit_to_token(fstring.middle_type());
+            if doubled_brace {
+                // CPython leaves the second brace out of every token
+                self.source.peek(1);
+                self.source.commit();
+            };
+            return Ok(());
+        };
+        if let ['{'] = self.source.peek(1) {
+            self.consume_replacement_field_start();
+            return Ok(());
+        };
+        if fstring.triple {
+            self.source.peek(2);
+        };
+        self.commit_to_token(fstring.end_type());
+        self.modes.pop();
+        Ok(())
+    }
+
+    /// Consume the next token from the format spec of a replacement field
+    fn consume_next_format_spec_token(&mut self) -> Result<(), TokenizeError> {
+        let fstring = self.current_fstring();
+        if let Err(e) = self.find_end_of_fstring_middle(fstring, true) {
+            self.report(e)?;
+            self.abandon_fstring();
+            return Ok(());
+        };
+        let text = self.source.peeked_index() > self.source.committed_index();
+        if text {
+            self.commit_to_token(fstring.middle_type());
+        };
+        match self.source.peek(1) {
+            ['{'] if !text => {
+                self.consume_replacement_field_start();
+                Ok(())
+            }
+            ['}'] => {
+                if self.options.cpython_compat && !text {
+                    // CPython always ends a format spec with its text, even when there is none
+                    self.add_empty_token(fstring.middle_type());
+                };
+                // the closing brace belongs to the replacement field
+                self.source.revert();
+                self.modes.pop();
+                self.consume_next_token()
+            }
+            _ if text => {
+                self.source.revert();
+                Ok(())
+            }
+            _ => {
+                self.report(TokenizeError::UnterminatedFString {
+                    start: fstring.start,
+                })?;
+                self.abandon_fstring();
+                Ok(())
+            }
         }
+    }
+
+    /// Open a replacement field at the brace that has been peeked
+    fn consume_replacement_field_start(&mut self) {
+        let start = self.source.position(self.source.committed_index());
+        self.commit_to_exact_token(TokenType::OP, TokenType::LBRACE);
+        self.brackets.push(('{', start));
+        self.modes.push(Mode::ReplacementField {
+            depth: self.nesting_level(),
+        });
+    }
+
+    /// Attempt to consume an op that has a special meaning directly within a replacement field
+    /// a conversion's "!", the colon starting a format spec, or the closing brace
+    fn consume_next_replacement_field_op(&mut self, depth: usize) -> bool {
+        if self.nesting_level() != depth {
+            return false;
+        };
+        match self.source.peek(2) {
+            ['!', '=', ..] => {
+                self.source.revert();
+                return false;
+            }
+            ['!', ..] => {
+                self.source.hide(1);
+                self.commit_to_exact_token(TokenType::OP, TokenType::EXCLAMATION);
+            }
+            [':', ..] => {
+                self.source.hide(1);
+                self.commit_to_exact_token(TokenType::OP, TokenType::COLON);
+                self.modes.push(Mode::FormatSpec);
+            }
+            ['}', ..] => {
+                self.source.hide(1);
+                self.commit_to_exact_token(TokenType::OP, TokenType::RBRACE);
+                self.brackets.pop();
+                self.modes.pop();
+            }
+            _ => {
+                self.source.revert();
+                return false;
+            }
+        };
+        true
+    }
+
+    fn finalize_stream(&mut self) -> Result<(), TokenizeError> {
+        while !self.modes.is_empty() {
+            self.report(TokenizeError::UnterminatedFString {
+                start: self.current_fstring().start,
+            })?;
+            self.abandon_fstring();
+        }
+        if let Some((_, start)) = self.brackets.first() {
+            self.report(TokenizeError::EofInMultiLineStatement { start: *start })?;
+            self.brackets.clear();
+        };
         if self.within_statement {
             // all statements must end in a newline, even if not present in the source
-            self.add_token(
-                TokenType::NEWLINE,
-                TokenType::NEWLINE,
-                String::from(""),
-                self.source.committed_index(),
-                self.source.committed_index() + 1,
-            );
+            self.add_missing_newline_token(TokenType::NEWLINE);
         };
         while self.indents_seen.len() > 1 {
             // bottom of the stack is indent of size 0 and does not need a DEDENT
             self.indents_seen.pop();
-            self.add_token(
-                TokenType::DEDENT,
-                TokenType::DEDENT,
-                String::from(""),
-                self.source.committed_index() + 1,
-                self.source.committed_index() + 1,
-            );
+            self.add_eof_token(TokenType::DEDENT);
         }
-        self.add_token(
-            TokenType::ENDMARKER,
-            TokenType::ENDMARKER,
-            String::from(""),
-            self.source.committed_index() + 1,
-            self.source.committed_index() + 1,
-        );
+        self.add_eof_token(TokenType::ENDMARKER);
         self.ended = true;
         Ok(())
     }
 
-    fn consume_next_token(&mut self) -> Result<(), String> {
+    fn consume_next_token(&mut self) -> Result<(), TokenizeError> {
         if self.ended {
             return Ok(());
         };
         if self.source.at_end() {
             return self.finalize_stream();
         };
+        let mut field_depth = None;
+        match self.modes.last() {
+            Some(Mode::FString(fstring)) => {
+                let fstring = *fstring;
+                return self.consume_next_fstring_token(fstring);
+            }
+            Some(Mode::FormatSpec) => return self.consume_next_format_spec_token(),
+            Some(Mode::ReplacementField { depth }) => field_depth = Some(*depth),
+            None => (),
+        };
 
         // consume any significant whitespace
         // may not produce a token, even if the cursor is advanced
@@ -938,21 +2234,31 @@ impl TokenStream {
                 Err(e) => return Err(e),
             };
         };
-        // non-dent whitespace does not produce tokens
+        // non-dent whitespace does not produce tokens, unless keeping trivia
         self.consume_next_whitespace();
+        let line_end = self.source.position(self.source.committed_index());
         if let Some(produced_token) = self.consume_next_newline() {
             if produced_token {
                 return Ok(());
             } else {
+                if self.options.cpython_compat && self.source.at_end() {
+                    // CPython's tokenize module expects the line after an escaped newline
+                    self.report(TokenizeError::EofInMultiLineStatement { start: line_end })?;
+                };
                 // re-enter; escaped newline is insignificant whitespace
                 return self.consume_next_token();
             };
         };
         // number must come before op to correctly capture a leading decimal point
-        if self.consume_next_number_token() {
+        if self.consume_next_number_token()? {
             return Ok(());
         };
-        if self.consume_next_op_token() {
+        if let Some(depth) = field_depth {
+            if self.consume_next_replacement_field_op(depth) {
+                return Ok(());
+            };
+        };
+        if self.consume_next_op_token()? {
             return Ok(());
         };
         // string must come before name to correctly capture prefix directives
@@ -972,24 +2278,46 @@ impl TokenStream {
 
         // no tokens found
         if self.source.at_end() {
-            return self.consume_next_token();
+            self.consume_next_token()
         } else {
-            self.source.peek(1);
+            let (c, followed) = match self.source.peek(2) {
+                [c, _] => (*c, true),
+                chars => (chars[0], false),
+            };
+            self.source.hide(1);
+            let start = self.source.position(self.source.committed_index());
+            if c == '\\' && !followed {
+                // a backslash ending the source has nothing after it to complain about
+                // but CPython's tokenize module still expects the line it continues
+                if self.options.cpython_compat {
+                    self.report(TokenizeError::EofInMultiLineStatement { start })?;
+                };
+            } else {
+                self.report(invalid_character(c, start))?;
+            };
             self.commit_to_token(TokenType::ERRORTOKEN);
-            return Ok(());
-        };
+            Ok(())
+        }
     }
 }
 
-impl Iterator for TokenStream {
-    type Item = Result<Token, String>;
-
-    fn next(&mut self) -> Option<Self::Item> {
+impl<'src> TokenStream<'src> {
+    /// Return the next token, borrowing its contents from the source whenever possible
+    /// no more tokens are returned after an error
+    pub fn next_token(&mut self) -> Option<Result<TokenRef<'src>, TokenizeError>> {
         if self.tokens.is_empty() {
-            match self.consume_next_token() {
-                Ok(_) => (),
-                Err(e) => return Some(Err(e)),
-            }
+            let mut consumed = self.consume_next_token();
+            // nothing found after the source could not be read is trustworthy
+            if let Some(e) = self.source.read_error.take() {
+                self.tokens.clear();
+                consumed = Err(e);
+            };
+            if let Err(e) = consumed {
+                // the problem is still there to be found again, and nothing after it would be
+                // trustworthy, so the stream ends here rather than repeating the error forever
+                self.ended = true;
+                return Some(Err(e));
+            };
         };
         if self.tokens.is_empty() {
             None
@@ -997,4 +2325,344 @@ impl Iterator for TokenStream {
             Ok(self.tokens.pop_front()).transpose()
         }
     }
+
+    /// Iterate over tokens that borrow their contents from the source whenever possible
+    pub fn borrowed(self) -> BorrowedTokens<'src> {
+        BorrowedTokens { stream: self }
+    }
+
+    /// Buffer the tokens behind a cursor that can look any distance ahead and move back
+    pub fn cursor(self) -> TokenCursor<'src> {
+        TokenCursor::new(self)
+    }
+
+    /// Every problem found in the source so far, in the order they were found
+    /// this is only ever populated when recovering, otherwise problems are returned as errors
+    pub fn diagnostics(&self) -> &[TokenizeError] {
+        &self.diagnostics
+    }
+}
+
+/// How much of a statement has been typed at an interactive prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completeness {
+    /// the statement is whole and can be run
+    Complete,
+    /// more lines are needed before the statement is whole
+    Incomplete,
+    /// no more lines can make the statement tokenize
+    Invalid,
+}
+
+/// Decide whether the lines typed at an interactive prompt are a whole statement,
+/// as `codeop.compile_command` does
+/// the lines are joined by newlines, so input ends in a newline only when its last line is empty
+/// a statement is incomplete within brackets or a triple quoted string, after a colon
+/// or line continuation, within a block not yet ended by an empty line,
+/// or after a decorator not yet followed by its definition
+/// a compound statement is a block even when written on one line, as in `if rye: bread`
+/// only the tokens are checked, so a statement that does not parse is left for the parser to reject
+pub fn completeness(input: &str, options: TokenizerOptions) -> Completeness {
+    let input = format!("{}\n", input);
+    let mut stream = TokenStream::with_options(
+        &input,
+        TokenizerOptions {
+            cpython_compat: false,
+            recover: false,
+            trivia: true,
+            single_input: true,
+            type_comments: false,
+            ..options
+        },
+    );
+    // the last token that is not whitespace, a comment, or the end of a line
+    let mut last_significant = None;
+    let mut previous = None;
+    // whether the statement is compound, so is only ended by an empty line
+    let mut compound = false;
+    let mut empty_line = false;
+    // whether the last line begun is a decorator, which must be followed by a definition
+    let mut line_start = true;
+    let mut decorator = false;
+    while let Some(found) = stream.next_token() {
+        let token = match found {
+            Ok(token) => token,
+            Err(
+                TokenizeError::UnterminatedTripleQuotedString { .. }
+                | TokenizeError::EofInMultiLineStatement { .. },
+            ) => return Completeness::Incomplete,
+            // the literal text of only a triple quoted f-string can continue onto the next line
+            // but a replacement field of any f-string can
+            Err(TokenizeError::UnterminatedFString { .. })
+                if match stream.modes.last() {
+                    Some(Mode::FString(fstring)) => fstring.triple,
+                    _ => true,
+                } =>
+            {
+                return Completeness::Incomplete
+            }
+            Err(_) => return Completeness::Invalid,
+        };
+        match token.exact_token_type {
+            TokenType::WHITESPACE | TokenType::COMMENT | TokenType::NL => (),
+            TokenType::INDENT | TokenType::DEDENT => (),
+            // a line continued onto an empty line ends there, unless that is the last line
+            TokenType::NEWLINE if !token.token_contents.is_empty() => {
+                // empty lines before the statement do not end it
+                if token.start.1 == 0 && last_significant.is_some() {
+                    empty_line = true;
+                };
+                if last_significant == Some(TokenType::CONTINUATION) && token.byte_end < input.len()
+                {
+                    last_significant = None;
+                };
+                line_start = true;
+            }
+            TokenType::NEWLINE => line_start = true,
+            // a block is only ended early by an empty line
+            // otherwise its DEDENT is found only at the end of the input
+            TokenType::ENDMARKER if previous == Some(TokenType::DEDENT) => {
+                return Completeness::Incomplete
+            }
+            TokenType::ENDMARKER => (),
+            // such as an unterminated single quoted string
+            TokenType::ERRORTOKEN => return Completeness::Invalid,
+            token_type => {
+                if last_significant.is_none() {
+                    // a match statement cannot be written on one line, so is left to its colon
+                    compound = token_type == TokenType::AT
+                        || matches!(
+                            &*token.token_contents,
+                            "if" | "while" | "for" | "try" | "with" | "def" | "class" | "async"
+                        );
+                };
+                if line_start && token_type != TokenType::CONTINUATION {
+                    decorator = token_type == TokenType::AT;
+                    line_start = false;
+                };
+                last_significant = Some(token_type);
+            }
+        };
+        previous = Some(token.exact_token_type);
+    }
+    match last_significant {
+        Some(TokenType::COLON | TokenType::CONTINUATION) => Completeness::Incomplete,
+        _ if decorator => Completeness::Incomplete,
+        _ if compound && !empty_line => Completeness::Incomplete,
+        _ => Completeness::Complete,
+    }
+}
+
+impl Iterator for TokenStream<'_> {
+    type Item = Result<Token, TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().map(|found| found.map(Token::from))
+    }
+}
+
+/// An iterator over the tokens of a `TokenStream` that does not copy their contents
+pub struct BorrowedTokens<'src> {
+    stream: TokenStream<'src>,
+}
+
+impl BorrowedTokens<'_> {
+    /// Every problem found in the source so far, see `TokenStream::diagnostics`
+    pub fn diagnostics(&self) -> &[TokenizeError] {
+        self.stream.diagnostics()
+    }
+}
+
+impl<'src> Iterator for BorrowedTokens<'src> {
+    type Item = Result<TokenRef<'src>, TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stream.next_token()
+    }
+}
+
+// every operator longer than one char, which two shorter operators written together could become
+const LONG_OPERATORS: [&str; 24] = [
+    "!=", "%=", "&=", "**", "**=", "*=", "+=", "-=", "->", "...", "//", "//=", "/=", ":=", "<<",
+    "<<=", "<=", "<>", "==", ">=", ">>", ">>=", "@=", "^=",
+];
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether two tokens without positions must be separated so that they are tokenized the same again
+fn needs_space(previous: &Token, next: &Token) -> bool {
+    if matches!(
+        previous.token_type,
+        TokenType::FSTRING_START
+            | TokenType::FSTRING_MIDDLE
+            | TokenType::TSTRING_START
+            | TokenType::TSTRING_MIDDLE
+    ) || matches!(
+        next.token_type,
+        TokenType::FSTRING_MIDDLE
+            | TokenType::FSTRING_END
+            | TokenType::TSTRING_MIDDLE
+            | TokenType::TSTRING_END
+    ) {
+        // the literal text of an f-string is never spaced
+        return false;
+    };
+    let (last, first) = match (
+        previous.token_contents.chars().next_back(),
+        next.token_contents.chars().next(),
+    ) {
+        (Some(last), Some(first)) => (last, first),
+        _ => return false,
+    };
+    match (previous.token_type, next.token_type) {
+        // adjacent strings would otherwise run into each other's quotes
+        (
+            TokenType::STRING | TokenType::FSTRING_END | TokenType::TSTRING_END,
+            TokenType::STRING | TokenType::FSTRING_START | TokenType::TSTRING_START,
+        ) => true,
+        // a name after a number would become part of it, as would a dot
+        (TokenType::NUMBER, _) => is_name_char(first) || first == '.',
+        (TokenType::OP, TokenType::NUMBER) => last == '.',
+        (TokenType::OP, TokenType::OP) => {
+            let joined: String = [last, first].iter().collect();
+            LONG_OPERATORS
+                .iter()
+                .any(|operator| operator.starts_with(&joined))
+        }
+        // a string prefix is a name
+        _ => is_name_char(last) && (is_name_char(first) || first == '\'' || first == '"'),
+    }
+}
+
+/// The brace that CPython compatible tokens leave out after the literal text of an f-string
+/// that text otherwise holds the source of the f-string, in which braces are always doubled
+fn undoubled_brace(previous: &Token) -> Option<char> {
+    if !matches!(
+        previous.token_type,
+        TokenType::FSTRING_MIDDLE | TokenType::TSTRING_MIDDLE
+    ) {
+        return None;
+    };
+    let brace = previous
+        .token_contents
+        .chars()
+        .next_back()
+        .filter(|c| *c == '{' || *c == '}')?;
+    let braces = previous
+        .token_contents
+        .chars()
+        .rev()
+        .take_while(|c| *c == brace)
+        .count();
+    (braces % 2 == 1).then_some(brace)
+}
+
+/// Turn tokens back into source that tokenizes the same
+///
+/// Tokens are spaced as far apart as their positions say, relative to the token before them,
+/// so tokens may be changed or replaced without moving the tokens after them.
+/// Backslashes continuing a line are written straight after the token before them,
+/// unless there are CONTINUATION tokens.
+/// Tokens without a position, those on line 0, are only spaced as much as they need to be.
+/// A brace left out of the literal text of an f-string, as CPython compatible tokens do,
+/// is put back, though when there are positions only where they leave room for it.
+/// INDENT and DEDENT become the indentation of the lines after them, an INDENT without contents
+/// being four spaces deeper than the block it is in.
+pub fn untokenize<I>(tokens: I) -> String
+where
+    I: IntoIterator<Item = Token>,
+{
+    let mut source = String::new();
+    let mut indents: Vec<String> = Vec::new();
+    let mut line_start = true;
+    let mut previous: Option<Token> = None;
+    // where the previous token ended, when it has a position
+    // after the end of a line this is the start of the next line
+    let mut previous_end: Option<(usize, usize)> = None;
+    for token in tokens {
+        match token.token_type {
+            TokenType::ENCODING => continue,
+            TokenType::ENDMARKER => break,
+            TokenType::INDENT => {
+                let indent = match token.token_contents.as_str() {
+                    "" => format!("{}    ", indents.last().map_or("", String::as_str)),
+                    indent => String::from(indent),
+                };
+                indents.push(indent);
+                continue;
+            }
+            TokenType::DEDENT => {
+                indents.pop();
+                continue;
+            }
+            _ => (),
+        };
+        let newline = matches!(token.token_type, TokenType::NEWLINE | TokenType::NL);
+        let position = Some(token.start).filter(|(line, _)| *line > 0);
+        if line_start {
+            let indent = indents.last().map_or("", String::as_str);
+            let indent_width = indent.chars().count();
+            match position {
+                Some((_, column)) if column < indent_width => {
+                    source.extend(std::iter::repeat_n(' ', column));
+                }
+                Some((_, column)) => {
+                    source.push_str(indent);
+                    source.extend(std::iter::repeat_n(' ', column - indent_width));
+                }
+                None if newline => (),
+                None => source.push_str(indent),
+            };
+        } else if let (Some((line, column)), Some((end_line, end_column)), Some(previous)) =
+            (position, previous_end, &previous)
+        {
+            if line > end_line {
+                // the line was continued with backslashes
+                for _ in end_line..line {
+                    source.push_str("\\\n");
+                }
+                source.extend(std::iter::repeat_n(' ', column));
+            } else if line == end_line && column >= end_column {
+                let mut gap = column - end_column;
+                let middle = matches!(
+                    previous.token_type,
+                    TokenType::FSTRING_MIDDLE | TokenType::TSTRING_MIDDLE
+                );
+                if middle && gap > 0 {
+                    // CPython compatible tokens leave the second brace of a doubled brace out
+                    if let Some(brace @ ('{' | '}')) = previous.token_contents.chars().next_back() {
+                        source.push(brace);
+                        gap -= 1;
+                    };
+                };
+                source.extend(std::iter::repeat_n(' ', gap));
+            } else if needs_space(previous, &token) {
+                source.push(' ');
+            };
+        } else if let Some(previous) = &previous {
+            if let Some(brace) = undoubled_brace(previous) {
+                source.push(brace);
+            } else if needs_space(previous, &token) {
+                source.push(' ');
+            };
+        };
+
+        if newline && token.token_contents.is_empty() && position.is_none() {
+            source.push('\n');
+        } else {
+            source.push_str(&token.token_contents);
+        };
+        previous_end = position.map(|_| {
+            if token.token_contents.ends_with(['\n', '\r']) {
+                (token.end.0 + 1, 0)
+            } else {
+                token.end
+            }
+        });
+        line_start = newline;
+        previous = Some(token);
+    }
+    source
 }
@@ -1,3 +1,11 @@
+use crate::keywords::{classify_name, NameKind};
+use crate::numbers::{parse_number, NumberValue};
+use crate::strings::{decode_string, is_prefix_letter, DecodeError, DecodedString, StringPrefix};
+use crate::tokenize::TokenizerOptions;
+use std::borrow::Cow;
+
+use unicode_normalization::{is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
 #[allow(non_camel_case_types)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum TokenType {
@@ -63,15 +71,25 @@ pub enum TokenType {
     RARROW,
     ELLIPSIS,
     COLONEQUAL,
+    EXCLAMATION,
     OP,
     AWAIT,
     ASYNC,
     TYPE_IGNORE,
     TYPE_COMMENT,
+    FSTRING_START,
+    FSTRING_MIDDLE,
+    FSTRING_END,
+    TSTRING_START,
+    TSTRING_MIDDLE,
+    TSTRING_END,
     ERRORTOKEN,
     COMMENT,
     NL,
     ENCODING,
+    // only produced when keeping trivia
+    WHITESPACE,
+    CONTINUATION,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -82,4 +100,216 @@ pub struct Token {
     // these are slice indices
     pub col_start: usize,
     pub col_end: usize,
+    // these are byte indices; unlike col_start and col_end they always slice the source
+    pub byte_start: usize,
+    pub byte_end: usize,
+    // these are (line, column) pairs; lines start at 1 and columns at 0
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+/// A token that borrows its contents from the source whenever possible
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenRef<'src> {
+    pub token_type: TokenType,
+    pub exact_token_type: TokenType,
+    pub token_contents: Cow<'src, str>,
+    // these are slice indices
+    pub col_start: usize,
+    pub col_end: usize,
+    // these are byte indices; unlike col_start and col_end they always slice the source
+    pub byte_start: usize,
+    pub byte_end: usize,
+    // these are (line, column) pairs; lines start at 1 and columns at 0
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+fn is_name(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::NAME | TokenType::ASYNC | TokenType::AWAIT
+    )
+}
+
+/// Split a comment into the type of type comment it is and the text it gives, as CPython does
+/// a type comment starts `# type:`, with any spaces or tabs after the `#` and the colon
+/// it is a TYPE_IGNORE when its text is `ignore`, alone or followed by tags such as `[code]`
+pub(crate) fn split_type_comment(comment: &str) -> Option<(TokenType, &str)> {
+    let text = comment
+        .strip_prefix('#')?
+        .trim_start_matches([' ', '\t'])
+        .strip_prefix("type:")?
+        .trim_start_matches([' ', '\t']);
+    match text.strip_prefix("ignore") {
+        // only an ascii symbol can follow, so `# type: ignored` is an annotation
+        Some(tags) if !tags.starts_with(|c: char| !c.is_ascii() || c.is_ascii_alphanumeric()) => {
+            Some((TokenType::TYPE_IGNORE, tags))
+        }
+        _ => Some((TokenType::TYPE_COMMENT, text)),
+    }
+}
+
+/// The text given by the contents of a token that is a type comment
+fn type_comment(token_type: TokenType, contents: &str) -> Option<&str> {
+    match split_type_comment(contents) {
+        Some((found, text)) if found == token_type => Some(text),
+        _ => None,
+    }
+}
+
+/// The NFKC normal form of the contents of a token that is a name
+fn normalized_name(token_type: TokenType, contents: &str) -> Option<Cow<'_, str>> {
+    if !is_name(token_type) {
+        return None;
+    };
+    match is_nfkc_quick(contents.chars()) {
+        IsNormalized::Yes => Some(Cow::Borrowed(contents)),
+        _ => Some(Cow::Owned(contents.nfkc().collect())),
+    }
+}
+
+impl Token {
+    /// Borrow this token's contents as a TokenRef
+    pub fn borrowed(&self) -> TokenRef<'_> {
+        TokenRef {
+            token_type: self.token_type,
+            exact_token_type: self.exact_token_type,
+            token_contents: Cow::Borrowed(&self.token_contents),
+            col_start: self.col_start,
+            col_end: self.col_end,
+            byte_start: self.byte_start,
+            byte_end: self.byte_end,
+            start: self.start,
+            end: self.end,
+        }
+    }
+
+    /// See `TokenRef::string_prefix`
+    pub fn string_prefix(&self) -> Option<StringPrefix> {
+        self.borrowed().string_prefix()
+    }
+
+    /// See `TokenRef::string_value`
+    pub fn string_value(
+        &self,
+        python_version: (u8, u8),
+    ) -> Option<Result<DecodedString, DecodeError>> {
+        self.borrowed().string_value(python_version)
+    }
+
+    /// See `TokenRef::number_value`
+    pub fn number_value(&self) -> Option<NumberValue> {
+        self.borrowed().number_value()
+    }
+
+    /// See `TokenRef::type_comment`
+    pub fn type_comment(&self) -> Option<&str> {
+        type_comment(self.token_type, &self.token_contents)
+    }
+
+    /// See `TokenRef::name_kind`
+    pub fn name_kind(&self, python_version: (u8, u8)) -> Option<NameKind> {
+        self.borrowed().name_kind(python_version)
+    }
+
+    /// See `TokenRef::normalized_name`
+    pub fn normalized_name(&self) -> Option<Cow<'_, str>> {
+        normalized_name(self.token_type, &self.token_contents)
+    }
+}
+
+impl TokenRef<'_> {
+    /// The prefix of this string literal; None when it is not a STRING, FSTRING_START or TSTRING_START
+    pub fn string_prefix(&self) -> Option<StringPrefix> {
+        if !matches!(
+            self.token_type,
+            TokenType::STRING | TokenType::FSTRING_START | TokenType::TSTRING_START
+        ) {
+            return None;
+        };
+        let contents = &*self.token_contents;
+        let letters = contents
+            .find(|c| !is_prefix_letter(c))
+            .map_or(contents, |quote| &contents[..quote]);
+        // no prefix was ever taken away, so the latest version accepts all of them
+        StringPrefix::parse(letters, TokenizerOptions::LATEST_VERSION)
+    }
+
+    /// The value of this string literal as the given (major, minor) version of Python evaluates it,
+    /// with any warnings about its escape sequences
+    /// None when it is not a STRING, or is an f-string whose value is only known when it runs
+    pub fn string_value(
+        &self,
+        python_version: (u8, u8),
+    ) -> Option<Result<DecodedString, DecodeError>> {
+        let prefix = self.string_prefix()?;
+        if self.token_type != TokenType::STRING || prefix.format || prefix.template {
+            return None;
+        };
+        Some(decode_string(
+            &self.token_contents,
+            prefix,
+            self.start,
+            python_version,
+        ))
+    }
+
+    /// The value of this numeric literal, without its underscores or radix prefix
+    /// None when it is not a NUMBER
+    pub fn number_value(&self) -> Option<NumberValue> {
+        match self.token_type {
+            TokenType::NUMBER => parse_number(&self.token_contents),
+            _ => None,
+        }
+    }
+
+    /// The annotation of this TYPE_COMMENT, or the tags following `ignore` in this TYPE_IGNORE,
+    /// such as `[code]`; None when it is neither
+    pub fn type_comment(&self) -> Option<&str> {
+        type_comment(self.token_type, &self.token_contents)
+    }
+
+    /// Whether this name is a keyword, soft keyword, builtin or plain identifier
+    /// in the given (major, minor) version of Python; None when it is not a name
+    /// keywords are only ever spelled as they are, but other names are resolved by their normal form
+    pub fn name_kind(&self, python_version: (u8, u8)) -> Option<NameKind> {
+        let normalized = self.normalized_name()?;
+        match classify_name(&self.token_contents, python_version) {
+            NameKind::Identifier
+                if classify_name(&normalized, python_version) == NameKind::Builtin =>
+            {
+                Some(NameKind::Builtin)
+            }
+            kind => Some(kind),
+        }
+    }
+
+    /// The name as Python resolves it, in NFKC normal form; None when it is not a name
+    /// names that differ only before normalization, like "\u{FB01}le" and "file", are the same name
+    /// this borrows the contents when they are already normalized, as all ascii names are
+    pub fn normalized_name(&self) -> Option<Cow<'_, str>> {
+        normalized_name(self.token_type, &self.token_contents)
+    }
+
+    /// Copy the contents out of the source
+    pub fn into_owned(self) -> Token {
+        Token {
+            token_type: self.token_type,
+            exact_token_type: self.exact_token_type,
+            token_contents: self.token_contents.into_owned(),
+            col_start: self.col_start,
+            col_end: self.col_end,
+            byte_start: self.byte_start,
+            byte_end: self.byte_end,
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+impl From<TokenRef<'_>> for Token {
+    fn from(token: TokenRef<'_>) -> Token {
+        token.into_owned()
+    }
 }
@@ -0,0 +1,79 @@
+use std::borrow::Cow;
+
+use rstest::*;
+
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
+use rye::tokens::{Token, TokenRef};
+
+#[rstest]
+#[case("rye = 'bread'\n")]
+#[case("if \u{E9}t\u{E9}:\n    cheese(1.5e3, \"\"\"\u{E9}\n\"\"\") # toast\n")]
+#[case("rye\r\n\tbread\r")]
+fn borrowed_tokens(#[case] source: &str) {
+    let owned = TokenStream::new(source)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    let borrowed = TokenStream::new(source)
+        .borrowed()
+        .collect::<Result<Vec<TokenRef>, TokenizeError>>()
+        .unwrap();
+
+    let source_range = source.as_bytes().as_ptr_range();
+    for token in borrowed.iter() {
+        match &token.token_contents {
+            Cow::Borrowed(contents) => {
+                if !contents.is_empty() {
+                    assert!(
+                        source_range.contains(&contents.as_ptr()),
+                        "Token contents {:?} were not borrowed from the source",
+                        contents
+                    );
+                };
+            }
+            Cow::Owned(contents) => panic!("Token contents {:?} were copied", contents),
+        };
+    }
+
+    let converted: Vec<Token> = borrowed.into_iter().map(Token::from).collect();
+    assert_eq!(converted, owned);
+}
+
+#[test]
+fn decoded_tokens_are_owned() {
+    let tokens = TokenStream::from_bytes(b"# coding: latin-1\nrye = '\xE9'\n")
+        .unwrap()
+        .borrowed()
+        .collect::<Result<Vec<TokenRef>, TokenizeError>>()
+        .unwrap();
+    assert!(tokens
+        .iter()
+        .filter(|token| !token.token_contents.is_empty())
+        .all(|token| matches!(token.token_contents, Cow::Owned(_))));
+    assert_eq!(tokens[5].token_contents, "'\u{E9}'");
+}
+
+#[test]
+fn borrowed_tokens_derive_the_same_values() {
+    let source = "rye = '\\q' + 0x1F  # type: int\n\u{FB01}le = print\n";
+    let options = TokenizerOptions {
+        type_comments: true,
+        ..TokenizerOptions::default()
+    };
+    let owned = TokenStream::with_options(source, options)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    let borrowed = TokenStream::with_options(source, options)
+        .borrowed()
+        .collect::<Result<Vec<TokenRef>, TokenizeError>>()
+        .unwrap();
+    assert_eq!(owned.len(), borrowed.len());
+    for (owned, borrowed) in owned.iter().zip(borrowed.iter()) {
+        assert_eq!(&owned.borrowed(), borrowed);
+        assert_eq!(owned.string_prefix(), borrowed.string_prefix());
+        assert_eq!(owned.string_value((3, 12)), borrowed.string_value((3, 12)));
+        assert_eq!(owned.number_value(), borrowed.number_value());
+        assert_eq!(owned.type_comment(), borrowed.type_comment());
+        assert_eq!(owned.name_kind((3, 12)), borrowed.name_kind((3, 12)));
+        assert_eq!(owned.normalized_name(), borrowed.normalized_name());
+    }
+}
@@ -1,5 +1,12 @@
+// the assertions below are kept as first written, which newer lints would rewrite
+#![allow(
+    clippy::format_in_format_args,
+    clippy::len_zero,
+    clippy::useless_conversion
+)]
 use rstest::*;
 
+use rye::tokenize::{TokenStream, TokenizerOptions};
 use rye::tokens::{Token, TokenType};
 
 pub mod common;
@@ -21,6 +28,10 @@ fn single_comment_token(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = check_single_token(source);
     assert_eq!(
         token_type,
@@ -35,3 +46,72 @@ fn single_comment_token(#[case] source: &str) {
         format!("{:?}", exact_token_type)
     );
 }
+
+fn type_comments() -> TokenizerOptions {
+    TokenizerOptions {
+        type_comments: true,
+        ..TokenizerOptions::default()
+    }
+}
+
+// every case agrees with ast.parse(source, type_comments=True)
+#[rstest]
+#[case("# type: int", TokenType::TYPE_COMMENT, Some("int"))]
+#[case("#type:int  ", TokenType::TYPE_COMMENT, Some("int  "))]
+#[case("#\ttype: List[int]", TokenType::TYPE_COMMENT, Some("List[int]"))]
+#[case("# type: ignored", TokenType::TYPE_COMMENT, Some("ignored"))]
+#[case("# type: ignore\u{E9}", TokenType::TYPE_COMMENT, Some("ignore\u{E9}"))]
+#[case("# type: ignore", TokenType::TYPE_IGNORE, Some(""))]
+#[case("#type:ignore", TokenType::TYPE_IGNORE, Some(""))]
+#[case("# type: ignore[code]", TokenType::TYPE_IGNORE, Some("[code]"))]
+#[case("# type: ignore_me", TokenType::TYPE_IGNORE, Some("_me"))]
+#[case(
+    "# type: ignore[misc]  # rye",
+    TokenType::TYPE_IGNORE,
+    Some("[misc]  # rye")
+)]
+#[case("# type : int", TokenType::COMMENT, None)]
+#[case("# types: int", TokenType::COMMENT, None)]
+#[case("# rye # type: int", TokenType::COMMENT, None)]
+fn type_comment_tokens(
+    #[case] comment: &str,
+    #[case] expected_type: TokenType,
+    #[case] text: Option<&str>,
+) {
+    let source = format!("rye = 1  {}\n", comment);
+    let tokens: Vec<Token> = TokenStream::with_options(&source, type_comments())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let found = &tokens[3];
+    assert_eq!(
+        (found.token_type, found.exact_token_type),
+        (expected_type, expected_type)
+    );
+    assert_eq!(found.token_contents, comment);
+    assert_eq!(found.type_comment(), text);
+    assert_eq!(tokens[4].token_type, TokenType::NEWLINE);
+
+    let found = TokenStream::new(&source).nth(3).unwrap().unwrap();
+    assert_eq!(found.token_type, TokenType::COMMENT);
+    assert_eq!(found.type_comment(), None);
+}
+
+#[test]
+fn type_comment_line() {
+    let token_types: Vec<TokenType> =
+        TokenStream::with_options("# type: ignore\nrye = 1\n", type_comments())
+            .map(|token| token.unwrap().token_type)
+            .collect();
+    assert_eq!(
+        token_types,
+        vec![
+            TokenType::TYPE_IGNORE,
+            TokenType::NL,
+            TokenType::NAME,
+            TokenType::OP,
+            TokenType::NUMBER,
+            TokenType::NEWLINE,
+            TokenType::ENDMARKER,
+        ]
+    );
+}
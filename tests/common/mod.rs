@@ -1,14 +1,44 @@
+// the assertions below are kept as first written, which newer lints would rewrite
+#![allow(
+    clippy::format_in_format_args,
+    clippy::len_zero,
+    clippy::useless_conversion
+)]
 #[cfg(test)]
-use rye::tokenize::TokenStream;
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
 use rye::tokens::{Token, TokenType};
 
+/// turn `source` into tokens with `options`
+/// assert that no problem is found with it
+pub fn tokenize(source: &str, options: TokenizerOptions) -> Vec<Token> {
+    TokenStream::with_options(source, options)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap()
+}
+
+/// turn the whole of `source` into tokens while recovering from errors
+/// return every token and every problem found
+pub fn recovered_tokens(source: &str) -> (Vec<Token>, Vec<TokenizeError>) {
+    let options = TokenizerOptions {
+        recover: true,
+        ..TokenizerOptions::default()
+    };
+    let mut stream = TokenStream::with_options(source, options);
+    let tokens = stream
+        .by_ref()
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    (tokens, stream.diagnostics().to_vec())
+}
+
 /// turn `source` into a stream of tokens
 /// perform minimum checks on the stream
 /// return the tokens, minus the mandatory ENDMARKER
 pub fn source_to_tokens(source: &str) -> Vec<Token> {
     let source_len = source.chars().count();
 
-    let maybe_tokens: Result<Vec<Token>, String> = TokenStream::new(source).into_iter().collect();
+    let maybe_tokens: Result<Vec<Token>, TokenizeError> =
+        TokenStream::new(source).into_iter().collect();
     assert!(
         maybe_tokens.is_ok(),
         "{} while tokenizing source {}",
@@ -29,6 +59,10 @@ pub fn source_to_tokens(source: &str) -> Vec<Token> {
         token_contents,
         col_start,
         col_end,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = tokens.pop().unwrap();
     assert_eq!(
         token_type,
@@ -78,6 +112,10 @@ pub fn check_single_token_statement(source: &str) -> Token {
         token_contents,
         col_start,
         col_end,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = tokens.pop().unwrap();
     assert_eq!(
         token_type,
@@ -103,6 +141,10 @@ pub fn check_single_token_statement(source: &str) -> Token {
         token_contents,
         col_start,
         col_end,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = tok.clone();
     assert_eq!(
         token_contents,
@@ -136,6 +178,10 @@ pub fn check_single_token(source: &str) -> Token {
         token_contents,
         col_start,
         col_end,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = tok.clone();
     assert_eq!(
         token_contents,
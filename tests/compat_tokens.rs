@@ -0,0 +1,217 @@
+use rstest::*;
+
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
+use rye::tokens::{Token, TokenType};
+
+type Found<'a> = (
+    TokenType,
+    TokenType,
+    &'a str,
+    (usize, usize),
+    (usize, usize),
+);
+
+fn compat() -> TokenizerOptions {
+    TokenizerOptions {
+        cpython_compat: true,
+        ..TokenizerOptions::default()
+    }
+}
+
+// expected tokens are the output of CPython 3.12's tokenize.generate_tokens
+#[rstest]
+#[case(
+    "rye = 0x1F + 1.5e3j\n# bread",
+    &[
+        (TokenType::NAME, TokenType::NAME, "rye", (1, 0), (1, 3)),
+        (TokenType::OP, TokenType::EQUAL, "=", (1, 4), (1, 5)),
+        (TokenType::NUMBER, TokenType::NUMBER, "0x1F", (1, 6), (1, 10)),
+        (TokenType::OP, TokenType::PLUS, "+", (1, 11), (1, 12)),
+        (TokenType::NUMBER, TokenType::NUMBER, "1.5e3j", (1, 13), (1, 19)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "\n", (1, 19), (1, 20)),
+        (TokenType::COMMENT, TokenType::COMMENT, "# bread", (2, 0), (2, 7)),
+        (TokenType::NL, TokenType::NL, "", (2, 7), (2, 8)),
+        (TokenType::ENDMARKER, TokenType::ENDMARKER, "", (3, 0), (3, 0)),
+    ]
+)]
+#[case(
+    "if rye:\n    bread # cheese",
+    &[
+        (TokenType::NAME, TokenType::NAME, "if", (1, 0), (1, 2)),
+        (TokenType::NAME, TokenType::NAME, "rye", (1, 3), (1, 6)),
+        (TokenType::OP, TokenType::COLON, ":", (1, 6), (1, 7)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "\n", (1, 7), (1, 8)),
+        (TokenType::INDENT, TokenType::INDENT, "    ", (2, 0), (2, 4)),
+        (TokenType::NAME, TokenType::NAME, "bread", (2, 4), (2, 9)),
+        (TokenType::COMMENT, TokenType::COMMENT, "# cheese", (2, 10), (2, 18)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "", (2, 18), (2, 19)),
+        (TokenType::DEDENT, TokenType::DEDENT, "", (3, 0), (3, 0)),
+        (TokenType::ENDMARKER, TokenType::ENDMARKER, "", (3, 0), (3, 0)),
+    ]
+)]
+#[case(
+    "rye = 0777 <> $bread?\n",
+    &[
+        (TokenType::NAME, TokenType::NAME, "rye", (1, 0), (1, 3)),
+        (TokenType::OP, TokenType::EQUAL, "=", (1, 4), (1, 5)),
+        (TokenType::NUMBER, TokenType::NUMBER, "0777", (1, 6), (1, 10)),
+        (TokenType::OP, TokenType::OP, "<>", (1, 11), (1, 13)),
+        (TokenType::OP, TokenType::OP, "$", (1, 14), (1, 15)),
+        (TokenType::NAME, TokenType::NAME, "bread", (1, 15), (1, 20)),
+        (TokenType::OP, TokenType::OP, "?", (1, 20), (1, 21)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "\n", (1, 21), (1, 22)),
+        (TokenType::ENDMARKER, TokenType::ENDMARKER, "", (2, 0), (2, 0)),
+    ]
+)]
+#[case(
+    "€ = cheese!\n",
+    &[
+        (TokenType::NAME, TokenType::NAME, "€", (1, 0), (1, 1)),
+        (TokenType::OP, TokenType::EQUAL, "=", (1, 2), (1, 3)),
+        (TokenType::NAME, TokenType::NAME, "cheese", (1, 4), (1, 10)),
+        (TokenType::OP, TokenType::EXCLAMATION, "!", (1, 10), (1, 11)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "\n", (1, 11), (1, 12)),
+        (TokenType::ENDMARKER, TokenType::ENDMARKER, "", (2, 0), (2, 0)),
+    ]
+)]
+#[case(
+    "rye = 1e+1e+bread",
+    &[
+        (TokenType::NAME, TokenType::NAME, "rye", (1, 0), (1, 3)),
+        (TokenType::OP, TokenType::EQUAL, "=", (1, 4), (1, 5)),
+        (TokenType::NUMBER, TokenType::NUMBER, "1e+1", (1, 6), (1, 10)),
+        (TokenType::NAME, TokenType::NAME, "e", (1, 10), (1, 11)),
+        (TokenType::OP, TokenType::PLUS, "+", (1, 11), (1, 12)),
+        (TokenType::NAME, TokenType::NAME, "bread", (1, 12), (1, 17)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "", (1, 17), (1, 18)),
+        (TokenType::ENDMARKER, TokenType::ENDMARKER, "", (2, 0), (2, 0)),
+    ]
+)]
+#[case(
+    "f'{{rye}}'",
+    &[
+        (TokenType::FSTRING_START, TokenType::FSTRING_START, "f'", (1, 0), (1, 2)),
+        (TokenType::FSTRING_MIDDLE, TokenType::FSTRING_MIDDLE, "{", (1, 2), (1, 3)),
+        (TokenType::FSTRING_MIDDLE, TokenType::FSTRING_MIDDLE, "rye}", (1, 4), (1, 8)),
+        (TokenType::FSTRING_END, TokenType::FSTRING_END, "'", (1, 9), (1, 10)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "", (1, 10), (1, 11)),
+        (TokenType::ENDMARKER, TokenType::ENDMARKER, "", (2, 0), (2, 0)),
+    ]
+)]
+#[case(
+    "f'{rye:{bread}}'",
+    &[
+        (TokenType::FSTRING_START, TokenType::FSTRING_START, "f'", (1, 0), (1, 2)),
+        (TokenType::OP, TokenType::LBRACE, "{", (1, 2), (1, 3)),
+        (TokenType::NAME, TokenType::NAME, "rye", (1, 3), (1, 6)),
+        (TokenType::OP, TokenType::COLON, ":", (1, 6), (1, 7)),
+        (TokenType::OP, TokenType::LBRACE, "{", (1, 7), (1, 8)),
+        (TokenType::NAME, TokenType::NAME, "bread", (1, 8), (1, 13)),
+        (TokenType::OP, TokenType::RBRACE, "}", (1, 13), (1, 14)),
+        (TokenType::FSTRING_MIDDLE, TokenType::FSTRING_MIDDLE, "", (1, 14), (1, 14)),
+        (TokenType::OP, TokenType::RBRACE, "}", (1, 14), (1, 15)),
+        (TokenType::FSTRING_END, TokenType::FSTRING_END, "'", (1, 15), (1, 16)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "", (1, 16), (1, 17)),
+        (TokenType::ENDMARKER, TokenType::ENDMARKER, "", (2, 0), (2, 0)),
+    ]
+)]
+#[case(
+    "f'''{rye}\nbread\n'''",
+    &[
+        (TokenType::FSTRING_START, TokenType::FSTRING_START, "f'''", (1, 0), (1, 4)),
+        (TokenType::OP, TokenType::LBRACE, "{", (1, 4), (1, 5)),
+        (TokenType::NAME, TokenType::NAME, "rye", (1, 5), (1, 8)),
+        (TokenType::OP, TokenType::RBRACE, "}", (1, 8), (1, 9)),
+        (TokenType::FSTRING_MIDDLE, TokenType::FSTRING_MIDDLE, "\nbread\n", (1, 9), (3, 0)),
+        (TokenType::FSTRING_END, TokenType::FSTRING_END, "'''", (3, 0), (3, 3)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "", (3, 3), (3, 4)),
+        (TokenType::ENDMARKER, TokenType::ENDMARKER, "", (4, 0), (4, 0)),
+    ]
+)]
+#[case(
+    "if rye:\n    bread\n  ",
+    &[
+        (TokenType::NAME, TokenType::NAME, "if", (1, 0), (1, 2)),
+        (TokenType::NAME, TokenType::NAME, "rye", (1, 3), (1, 6)),
+        (TokenType::OP, TokenType::COLON, ":", (1, 6), (1, 7)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "\n", (1, 7), (1, 8)),
+        (TokenType::INDENT, TokenType::INDENT, "    ", (2, 0), (2, 4)),
+        (TokenType::NAME, TokenType::NAME, "bread", (2, 4), (2, 9)),
+        (TokenType::NEWLINE, TokenType::NEWLINE, "\n", (2, 9), (2, 10)),
+        (TokenType::NL, TokenType::NL, "", (3, 2), (3, 3)),
+        (TokenType::DEDENT, TokenType::DEDENT, "", (4, 0), (4, 0)),
+        (TokenType::ENDMARKER, TokenType::ENDMARKER, "", (4, 0), (4, 0)),
+    ]
+)]
+#[case(
+    " ",
+    &[
+        (TokenType::NL, TokenType::NL, "", (1, 1), (1, 2)),
+        (TokenType::ENDMARKER, TokenType::ENDMARKER, "", (2, 0), (2, 0)),
+    ]
+)]
+fn compatible_tokens(#[case] source: &str, #[case] expected: &[Found]) {
+    let tokens = TokenStream::with_options(source, compat())
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    let found: Vec<Found> = tokens
+        .iter()
+        .map(|token| {
+            (
+                token.token_type,
+                token.exact_token_type,
+                token.token_contents.as_str(),
+                token.start,
+                token.end,
+            )
+        })
+        .collect();
+    assert_eq!(found, expected);
+}
+
+// the parser is left to reject a closer with nothing open, which does not continue the statement
+#[rstest]
+#[case("rye)", 1)]
+#[case("rye]\nbread\n", 2)]
+#[case("(rye)}\n", 1)]
+#[case("a)\nb\n", 2)]
+fn unbalanced_closer(#[case] source: &str, #[case] newlines: usize) {
+    let tokens = TokenStream::with_options(source, compat())
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    assert_eq!(
+        tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::NEWLINE)
+            .count(),
+        newlines
+    );
+}
+
+#[rstest]
+#[case("'rye\n", TokenizeError::UnterminatedString { start: (1, 0) })]
+#[case("rye = 1_\n", TokenizeError::InvalidNumber { radix: 10, start: (1, 6) })]
+#[case("rye = 0x\n", TokenizeError::InvalidNumber { radix: 16, start: (1, 6) })]
+#[case("rye = 0_\n", TokenizeError::InvalidNumber { radix: 10, start: (1, 6) })]
+#[case("rye = 1e+\n", TokenizeError::InvalidNumber { radix: 10, start: (1, 6) })]
+#[case("rye = 0b12\n", TokenizeError::InvalidDigit { digit: '2', radix: 2, start: (1, 6) })]
+#[case("rye = \\\n", TokenizeError::EofInMultiLineStatement { start: (1, 6) })]
+#[case("rye \\", TokenizeError::EofInMultiLineStatement { start: (1, 4) })]
+#[case("rye \\ bread\n", TokenizeError::UnexpectedCharacterAfterContinuation { start: (1, 4) })]
+fn incompatible_source(#[case] source: &str, #[case] expected: TokenizeError) {
+    let error = TokenStream::with_options(source, compat())
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap_err();
+    assert_eq!(error, expected);
+}
+
+#[rstest]
+#[case("rye)")]
+#[case("# rye")]
+#[case("0o17")]
+fn default_is_not_compatible(#[case] source: &str) {
+    let default: Vec<Result<Token, TokenizeError>> = TokenStream::new(source).collect();
+    let compatible: Vec<Result<Token, TokenizeError>> =
+        TokenStream::with_options(source, compat()).collect();
+    assert_ne!(default, compatible);
+}
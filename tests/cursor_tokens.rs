@@ -0,0 +1,98 @@
+use rstest::*;
+
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
+use rye::tokens::{Token, TokenRef, TokenType};
+
+fn contents(found: Option<Result<&TokenRef, TokenizeError>>) -> Option<String> {
+    found.map(|token| token.unwrap().token_contents.to_string())
+}
+
+#[rstest]
+#[case("rye = (1,\n  2)\n")]
+#[case("if rye:\n    bread = f'{toast!r}'\n")]
+#[case("")]
+fn same_tokens(#[case] source: &str) {
+    let expected = TokenStream::new(source)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    let mut cursor = TokenStream::new(source).cursor();
+    for (n, token) in expected.iter().enumerate() {
+        assert_eq!(
+            cursor.peek_nth(n).unwrap().unwrap().clone().into_owned(),
+            *token
+        );
+    }
+    assert!(cursor.peek_nth(expected.len()).is_none());
+    for token in &expected {
+        assert_eq!(
+            cursor.next_token().unwrap().unwrap().clone().into_owned(),
+            *token
+        );
+    }
+    assert!(cursor.next_token().is_none());
+    assert!(cursor.peek().is_none());
+}
+
+#[test]
+fn mark_and_reset() {
+    let mut cursor = TokenStream::new("rye = bread\n").cursor();
+    let start = cursor.mark();
+    assert_eq!(contents(cursor.next_token()), Some(String::from("rye")));
+    let equal = cursor.mark();
+    assert_eq!(contents(cursor.peek_nth(1)), Some(String::from("bread")));
+    assert_eq!(contents(cursor.next_token()), Some(String::from("=")));
+    assert!(equal > start);
+
+    cursor.reset(start);
+    assert_eq!(cursor.mark(), start);
+    assert_eq!(contents(cursor.peek()), Some(String::from("rye")));
+    assert_eq!(contents(cursor.peek_nth(2)), Some(String::from("bread")));
+
+    // a mark can be returned to after resetting before it
+    cursor.reset(equal);
+    assert_eq!(contents(cursor.next_token()), Some(String::from("=")));
+    assert_eq!(contents(cursor.next_token()), Some(String::from("bread")));
+}
+
+#[test]
+fn reset_past_error() {
+    let mut cursor = TokenStream::new("rye = )\n").cursor();
+    let start = cursor.mark();
+    let error = TokenizeError::UnbalancedCloser {
+        closer: ')',
+        start: (1, 6),
+    };
+    assert_eq!(cursor.peek_nth(2), Some(Err(error.clone())));
+    assert!(cursor.peek_nth(3).is_none());
+    assert_eq!(contents(cursor.next_token()), Some(String::from("rye")));
+    assert_eq!(contents(cursor.next_token()), Some(String::from("=")));
+    // the cursor never moves past the error
+    assert_eq!(cursor.next_token(), Some(Err(error.clone())));
+    assert_eq!(cursor.next_token(), Some(Err(error.clone())));
+    cursor.reset(start);
+    assert_eq!(contents(cursor.next_token()), Some(String::from("rye")));
+}
+
+#[test]
+fn recovered_diagnostics() {
+    let options = TokenizerOptions {
+        recover: true,
+        ..TokenizerOptions::default()
+    };
+    let mut cursor = TokenStream::with_options("rye = $\n", options).cursor();
+    assert!(cursor.diagnostics().is_empty());
+    let found = cursor.peek_nth(2).unwrap().unwrap();
+    assert_eq!(found.token_type, TokenType::ERRORTOKEN);
+    assert_eq!(cursor.diagnostics().len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "Cannot reset to a mark not made by this cursor")]
+fn foreign_mark() {
+    let mut other = TokenStream::new("rye = bread\n").cursor();
+    other.next_token();
+    other.next_token();
+    let mark = other.mark();
+    let mut cursor = TokenStream::new("rye = bread\n").cursor();
+    cursor.reset(mark);
+}
@@ -1,9 +1,13 @@
-#![feature(assert_matches)]
-use std::assert_matches::assert_matches;
-
+// the assertions below are kept as first written, which newer lints would rewrite
+#![allow(
+    clippy::assign_op_pattern,
+    clippy::format_in_format_args,
+    clippy::len_zero,
+    clippy::useless_conversion
+)]
 use rstest::*;
 
-use rye::tokenize::TokenStream;
+use rye::tokenize::{TokenStream, TokenizeError};
 use rye::tokens::{Token, TokenType};
 
 pub mod common;
@@ -72,10 +76,11 @@ done
 )]
 #[case(
     "
-        rye
-\tcheese
+\trye
+\t    cheese
+\tbread
 ",
-    1
+    2
 )]
 fn dent_tokens(#[case] source: &str, #[case] total_indents: usize) {
     let mut indents_found = 0;
@@ -88,6 +93,10 @@ fn dent_tokens(#[case] source: &str, #[case] total_indents: usize) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } in tokens.iter()
     {
         if *token_type == TokenType::INDENT {
@@ -134,10 +143,33 @@ fn unmatched_dent() {
   unmatched
 ",
     );
-    assert_matches!(
-        maybe_tokens
-            .into_iter()
-            .collect::<Result<Vec<Token>, String>>(),
-        Err(_)
+    assert_eq!(
+        maybe_tokens.collect::<Result<Vec<Token>, TokenizeError>>(),
+        Err(TokenizeError::DedentMismatch { start: (5, 2) })
     );
 }
+
+#[rstest]
+#[case("\n        rye\n\tcheese\n", (3, 0))]
+#[case("if rye:\n        bread\n\tcheese\n", (3, 0))]
+#[case("if rye:\n    \tbread\n\tcheese\n", (3, 0))]
+#[case("if rye:\n  \tbread\n\tcheese\n", (3, 0))]
+#[case("if rye:\n        if bread:\n\t cheese\n", (3, 0))]
+#[case("if rye:\n\tif bread:\n\t\tcheese\n        toast\n", (4, 0))]
+fn inconsistent_tabs(#[case] source: &str, #[case] start: (usize, usize)) {
+    assert_eq!(
+        TokenStream::new(source).collect::<Result<Vec<Token>, TokenizeError>>(),
+        Err(TokenizeError::InconsistentTabs { start })
+    );
+}
+
+#[test]
+fn partial_dedent() {
+    let dedents: Vec<(usize, usize)> =
+        source_to_tokens("if rye:\n  if bread:\n    cheese\n  toast\njam\n")
+            .iter()
+            .filter(|token| token.token_type == TokenType::DEDENT)
+            .map(|token| token.start)
+            .collect();
+    assert_eq!(dedents, vec![(4, 2), (5, 0)]);
+}
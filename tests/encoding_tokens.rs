@@ -0,0 +1,106 @@
+use rstest::*;
+
+use rye::encoding::detect_encoding;
+use rye::tokenize::{TokenStream, TokenizeError};
+use rye::tokens::{Token, TokenType};
+
+#[rstest]
+#[case(b"", "utf-8")]
+#[case(b"rye = 1\n", "utf-8")]
+#[case(b"\xEF\xBB\xBFrye = 1\n", "utf-8-sig")]
+#[case(b"# -*- coding: latin-1 -*-\n", "iso-8859-1")]
+#[case(b"# -*- coding: Latin_1 -*-\n", "iso-8859-1")]
+#[case(b"# vim: set fileencoding=cp1252 :\n", "cp1252")]
+#[case(b"#!/usr/bin/env python\n# coding=utf_8\n", "utf-8")]
+#[case(b"\n  # coding: iso-8859-15\n", "iso-8859-15")]
+#[case(b"\xEF\xBB\xBF# coding: utf-8\n", "utf-8-sig")]
+#[case(b"rye = 1\n# coding: latin-1\n", "utf-8")]
+#[case(b"rye = 1 # coding: latin-1\n", "utf-8")]
+#[case(b"\n\n# coding: latin-1\n", "utf-8")]
+#[case(b"#coding:\n", "utf-8")]
+fn detected_encoding(#[case] source: &[u8], #[case] encoding: &str) {
+    assert_eq!(detect_encoding(source), Ok(String::from(encoding)));
+}
+
+#[rstest]
+#[case(b"rye = '\xC3\xA9'", "utf-8", "'\u{E9}'")]
+#[case(b"\xEF\xBB\xBFrye = '\xC3\xA9'", "utf-8-sig", "'\u{E9}'")]
+#[case(b"# coding: latin-1\nrye = '\xE9\x80'", "iso-8859-1", "'\u{E9}\u{80}'")]
+#[case(b"# coding: cp1252\nrye = '\xE9\x80'", "cp1252", "'\u{E9}\u{20AC}'")]
+#[case(b"# coding: ascii\nrye = 'e'", "ascii", "'e'")]
+#[case(b"# coding: koi8-r\nrye = '\xC1'", "koi8-r", "'\u{430}'")]
+#[case(b"# coding: latin2\nrye = '\xB1'", "latin2", "'\u{105}'")]
+#[case(b"# coding: mac_roman\nrye = '\x8E'", "mac_roman", "'\u{E9}'")]
+#[case(b"# coding: cp949\nrye = '\xB0\xA1'", "cp949", "'\u{AC00}'")]
+fn decoded_tokens(#[case] source: &[u8], #[case] encoding: &str, #[case] string: &str) {
+    let tokens = TokenStream::from_bytes(source)
+        .unwrap()
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    let Token {
+        token_type,
+        exact_token_type: _,
+        token_contents,
+        col_start,
+        col_end,
+        byte_start: _,
+        byte_end: _,
+        start,
+        end,
+    } = &tokens[0];
+    assert_eq!(*token_type, TokenType::ENCODING);
+    assert_eq!(token_contents, encoding);
+    assert_eq!((*col_start, *col_end), (0, 0));
+    assert_eq!((*start, *end), ((0, 0), (0, 0)));
+
+    let found = tokens
+        .iter()
+        .find(|token| token.token_type == TokenType::STRING)
+        .unwrap();
+    assert_eq!(found.token_contents, string);
+}
+
+#[rstest]
+#[case(
+    b"# coding: rye\n",
+    TokenizeError::UnknownEncoding { encoding: String::from("rye"), start: (1, 0) }
+)]
+#[case(
+    b"\n# coding: utf-16\n",
+    TokenizeError::UnknownEncoding { encoding: String::from("utf-16"), start: (2, 0) }
+)]
+#[case(
+    b"\xEF\xBB\xBF# coding: latin-1\n",
+    TokenizeError::BomEncodingMismatch { encoding: String::from("iso-8859-1"), start: (1, 0) }
+)]
+#[case(
+    b"rye = 1\nbread = '\xE9'\n",
+    TokenizeError::UndecodableSource { encoding: String::from("utf-8"), start: (2, 9) }
+)]
+#[case(
+    b"# coding: ascii\nrye = '\xE9'\n",
+    TokenizeError::UndecodableSource { encoding: String::from("ascii"), start: (2, 7) }
+)]
+#[case(
+    b"# coding: iso-8859-9\n",
+    TokenizeError::UnknownEncoding { encoding: String::from("iso-8859-9"), start: (1, 0) }
+)]
+#[case(
+    b"# coding: gb2312\n",
+    TokenizeError::UnknownEncoding { encoding: String::from("gb2312"), start: (1, 0) }
+)]
+#[case(
+    b"# coding: shift_jis\n",
+    TokenizeError::UnknownEncoding { encoding: String::from("shift_jis"), start: (1, 0) }
+)]
+#[case(
+    b"# coding: cp1252\nrye = '\x81'\n",
+    TokenizeError::UndecodableSource { encoding: String::from("cp1252"), start: (2, 7) }
+)]
+#[case(
+    b"# coding: windows-1251\nrye = '\xC1'\n\x98",
+    TokenizeError::UndecodableSource { encoding: String::from("windows-1251"), start: (3, 0) }
+)]
+fn undecodable_source(#[case] source: &[u8], #[case] error: TokenizeError) {
+    assert_eq!(TokenStream::from_bytes(source).err(), Some(error));
+}
@@ -1,49 +1,104 @@
-#![feature(assert_matches)]
-use std::assert_matches::assert_matches;
-
+// the assertions below are kept as first written, which newer lints would rewrite
+#![allow(
+    clippy::format_in_format_args,
+    clippy::len_zero,
+    clippy::useless_conversion
+)]
 use rstest::*;
 
-use rye::tokenize::TokenStream;
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
 use rye::tokens::{Token, TokenType};
 
 pub mod common;
 use common::source_to_tokens;
 
 #[rstest]
-#[case("'''''")]
-#[case("''''")]
+#[case("'''''", TokenizeError::UnterminatedTripleQuotedString { start: (1, 0) })]
+#[case("''''", TokenizeError::UnterminatedTripleQuotedString { start: (1, 0) })]
 #[case(
     "
 \"\"\"
     rye
-"
+",
+    TokenizeError::UnterminatedTripleQuotedString { start: (2, 0) }
+)]
+#[case("rye = rb'''", TokenizeError::UnterminatedTripleQuotedString { start: (1, 6) })]
+#[case("(", TokenizeError::EofInMultiLineStatement { start: (1, 0) })]
+#[case("[", TokenizeError::EofInMultiLineStatement { start: (1, 0) })]
+#[case("{", TokenizeError::EofInMultiLineStatement { start: (1, 0) })]
+#[case("rye\nbread(\n[\n", TokenizeError::EofInMultiLineStatement { start: (2, 5) })]
+#[case(")", TokenizeError::UnbalancedCloser { closer: ')', start: (1, 0) })]
+#[case("rye]", TokenizeError::UnbalancedCloser { closer: ']', start: (1, 3) })]
+#[case("(rye)\n}", TokenizeError::UnbalancedCloser { closer: '}', start: (2, 0) })]
+#[case(
+    "(]",
+    TokenizeError::MismatchedCloser { closer: ']', opener: '(', opener_start: (1, 0), start: (1, 1) }
 )]
-#[case("(")]
-#[case("[")]
-#[case("{")]
-fn tokenizing_error(#[case] source: &str) {
+#[case(
+    "rye = {\n  'bread': [1, 2)\n}",
+    TokenizeError::MismatchedCloser { closer: ')', opener: '[', opener_start: (2, 11), start: (2, 16) }
+)]
+#[case(
+    "f'{rye)}'",
+    TokenizeError::MismatchedCloser { closer: ')', opener: '{', opener_start: (1, 2), start: (1, 6) }
+)]
+fn tokenizing_error(#[case] source: &str, #[case] error: TokenizeError) {
     let maybe_tokens = TokenStream::new(source);
-    assert_matches!(
-        maybe_tokens
-            .into_iter()
-            .collect::<Result<Vec<Token>, String>>(),
-        Err(_)
+    assert_eq!(
+        maybe_tokens.collect::<Result<Vec<Token>, TokenizeError>>(),
+        Err(error)
     );
 }
 
 #[rstest]
-#[case("?")]
-#[case("!")]
+#[case("rye = '''bread")]
+#[case("(rye")]
+#[case("rye)\nbread\n")]
+fn nothing_after_error(#[case] source: &str) {
+    let mut tokens = TokenStream::new(source);
+    assert!(tokens.by_ref().any(|token| token.is_err()));
+    assert_eq!(tokens.next(), None);
+    assert_eq!(tokens.next(), None);
+}
+
+#[test]
+fn tokenizing_error_message() {
+    let error = TokenizeError::EofInMultiLineStatement { start: (3, 4) };
+    assert_eq!(error.start(), (3, 4));
+    assert_eq!(
+        error.to_string(),
+        "EOF in multi-line statement (line 3, column 4)"
+    );
+}
+
+#[test]
+fn bracket_error_message() {
+    let error = TokenizeError::MismatchedCloser {
+        closer: ']',
+        opener: '(',
+        opener_start: (1, 0),
+        start: (2, 4),
+    };
+    assert_eq!(
+        error.to_string(),
+        "closing bracket ']' does not match opening bracket '(' on line 1, column 0 (line 2, column 4)"
+    );
+    let error = TokenizeError::UnbalancedCloser {
+        closer: ')',
+        start: (1, 3),
+    };
+    assert_eq!(error.to_string(), "unmatched ')' (line 1, column 3)");
+}
+
+#[rstest]
 #[case("'  rye  ")]
 #[case("'  rye  \n")]
 #[case("'  rye  \n'")]
 #[case("'rye\"")]
-#[case("'rye\\'")]
 #[case("\"  rye  ")]
 #[case("\"  rye  \n")]
 #[case("\"  rye  \n\"")]
 #[case("\"rye'")]
-#[case("\"rye\\\"")]
 fn errortoken(#[case] source: &str) {
     let tokens = source_to_tokens(source);
     assert!(
@@ -58,6 +113,10 @@ fn errortoken(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = tokens[0];
     assert_eq!(
         token_type,
@@ -79,7 +138,105 @@ fn errortoken(#[case] source: &str) {
             token_contents: _,
             col_start: _,
             col_end: _,
+            byte_start: _,
+            byte_end: _,
+            start: _,
+            end: _,
         } = token;
         assert_ne!(token_type, TokenType::STRING, "Got unexpected STRING Token");
     }
 }
+
+#[rstest]
+#[case("rye ? bread", TokenizeError::InvalidCharacter { character: '?', suggestion: None, start: (1, 4) })]
+#[case("rye ! bread", TokenizeError::InvalidCharacter { character: '!', suggestion: None, start: (1, 4) })]
+#[case("$rye", TokenizeError::InvalidCharacter { character: '$', suggestion: None, start: (1, 0) })]
+#[case("`rye`", TokenizeError::InvalidCharacter { character: '`', suggestion: None, start: (1, 0) })]
+#[case("rye\u{20AC}", TokenizeError::InvalidCharacter { character: '\u{20AC}', suggestion: None, start: (1, 3) })]
+#[case(
+    "print(\u{201C}rye\u{201D})",
+    TokenizeError::InvalidCharacter { character: '\u{201C}', suggestion: Some('"'), start: (1, 6) }
+)]
+#[case(
+    "print\u{FF08}rye)",
+    TokenizeError::InvalidCharacter { character: '\u{FF08}', suggestion: Some('('), start: (1, 5) }
+)]
+#[case(
+    "rye \u{2212} 1",
+    TokenizeError::InvalidCharacter { character: '\u{2212}', suggestion: Some('-'), start: (1, 4) }
+)]
+#[case(
+    "rye\u{200B}",
+    TokenizeError::InvalidNonPrintableCharacter { character: '\u{200B}', suggestion: None, start: (1, 3) }
+)]
+#[case(
+    "rye =\u{A0}1",
+    TokenizeError::InvalidNonPrintableCharacter { character: '\u{A0}', suggestion: Some(' '), start: (1, 5) }
+)]
+#[case(
+    "rye\0",
+    TokenizeError::InvalidNonPrintableCharacter { character: '\0', suggestion: None, start: (1, 3) }
+)]
+#[case("rye \\ bread", TokenizeError::UnexpectedCharacterAfterContinuation { start: (1, 4) })]
+// the backslash is left over from a string that was never closed
+#[case("'rye\\'", TokenizeError::UnexpectedCharacterAfterContinuation { start: (1, 4) })]
+#[case("\"rye\\\"", TokenizeError::UnexpectedCharacterAfterContinuation { start: (1, 4) })]
+fn invalid_character(#[case] source: &str, #[case] error: TokenizeError) {
+    assert_eq!(
+        TokenStream::new(source).collect::<Result<Vec<Token>, TokenizeError>>(),
+        Err(error)
+    );
+}
+
+#[test]
+fn invalid_character_message() {
+    let error = TokenizeError::InvalidCharacter {
+        character: '\u{20AC}',
+        suggestion: None,
+        start: (1, 3),
+    };
+    assert_eq!(
+        error.to_string(),
+        "invalid character '\u{20AC}' (U+20AC) (line 1, column 3)"
+    );
+    let error = TokenizeError::InvalidCharacter {
+        character: '\u{2018}',
+        suggestion: Some('\''),
+        start: (1, 0),
+    };
+    assert_eq!(
+        error.to_string(),
+        "invalid character '\u{2018}' (U+2018), did you mean '\''? (line 1, column 0)"
+    );
+    let error = TokenizeError::InvalidNonPrintableCharacter {
+        character: '\u{200B}',
+        suggestion: None,
+        start: (2, 1),
+    };
+    assert_eq!(
+        error.to_string(),
+        "invalid non-printable character U+200B (line 2, column 1)"
+    );
+}
+
+#[rstest]
+#[case("rye $ bread", TokenType::OP)]
+#[case("rye \u{200B}", TokenType::NAME)]
+fn invalid_character_compat(#[case] source: &str, #[case] token_type: TokenType) {
+    let options = TokenizerOptions {
+        cpython_compat: true,
+        ..TokenizerOptions::default()
+    };
+    let tokens = TokenStream::with_options(source, options)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    assert_eq!(tokens[1].token_type, token_type);
+    assert_eq!(tokens[1].start, (1, 4));
+}
+
+#[test]
+fn backslash_ending_source() {
+    let tokens = source_to_tokens("rye \\");
+    assert_eq!(tokens[1].token_type, TokenType::ERRORTOKEN);
+    assert_eq!(tokens[1].token_contents, "\\");
+}
@@ -0,0 +1,205 @@
+use rstest::*;
+
+use rye::tokenize::{TokenStream, TokenizeError};
+use rye::tokens::TokenType;
+
+pub mod common;
+use common::source_to_tokens;
+
+type Found<'a> = (TokenType, &'a str);
+
+#[rstest]
+#[case("f''", &[(TokenType::FSTRING_START, "f'"), (TokenType::FSTRING_END, "'")])]
+#[case(
+    "F\"rye\"",
+    &[
+        (TokenType::FSTRING_START, "F\""),
+        (TokenType::FSTRING_MIDDLE, "rye"),
+        (TokenType::FSTRING_END, "\""),
+    ]
+)]
+#[case(
+    "rf'''rye\n\\{bread}'''",
+    &[
+        (TokenType::FSTRING_START, "rf'''"),
+        (TokenType::FSTRING_MIDDLE, "rye\n\\"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "bread"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'''"),
+    ]
+)]
+#[case(
+    "f'{{rye}} \\'{bread}'",
+    &[
+        (TokenType::FSTRING_START, "f'"),
+        (TokenType::FSTRING_MIDDLE, "{{rye}} \\'"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "bread"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'"),
+    ]
+)]
+#[case(
+    "f'\\N{BULLET} {rye}'",
+    &[
+        (TokenType::FSTRING_START, "f'"),
+        (TokenType::FSTRING_MIDDLE, "\\N{BULLET} "),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "rye"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'"),
+    ]
+)]
+#[case(
+    "f\"{rye!r:>{width}.2}\"",
+    &[
+        (TokenType::FSTRING_START, "f\""),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "rye"),
+        (TokenType::EXCLAMATION, "!"),
+        (TokenType::NAME, "r"),
+        (TokenType::COLON, ":"),
+        (TokenType::FSTRING_MIDDLE, ">"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "width"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_MIDDLE, ".2"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "\""),
+    ]
+)]
+#[case(
+    "f'{rye = !s}'",
+    &[
+        (TokenType::FSTRING_START, "f'"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "rye"),
+        (TokenType::EQUAL, "="),
+        (TokenType::EXCLAMATION, "!"),
+        (TokenType::NAME, "s"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'"),
+    ]
+)]
+#[case(
+    "f'{rye != bread[1:2]}'",
+    &[
+        (TokenType::FSTRING_START, "f'"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "rye"),
+        (TokenType::NOTEQUAL, "!="),
+        (TokenType::NAME, "bread"),
+        (TokenType::LSQB, "["),
+        (TokenType::NUMBER, "1"),
+        (TokenType::COLON, ":"),
+        (TokenType::NUMBER, "2"),
+        (TokenType::RSQB, "]"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'"),
+    ]
+)]
+#[case(
+    "f'{ {\"rye\": 1}[\"rye\"] }'",
+    &[
+        (TokenType::FSTRING_START, "f'"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::STRING, "\"rye\""),
+        (TokenType::COLON, ":"),
+        (TokenType::NUMBER, "1"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::LSQB, "["),
+        (TokenType::STRING, "\"rye\""),
+        (TokenType::RSQB, "]"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'"),
+    ]
+)]
+#[case(
+    "f'{f'{rye}'}'",
+    &[
+        (TokenType::FSTRING_START, "f'"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::FSTRING_START, "f'"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "rye"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'"),
+    ]
+)]
+#[case(
+    "f'''{\n  rye # bread\n}'''",
+    &[
+        (TokenType::FSTRING_START, "f'''"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NL, "\n"),
+        (TokenType::NAME, "rye"),
+        (TokenType::COMMENT, "# bread"),
+        (TokenType::NL, "\n"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'''"),
+    ]
+)]
+#[case("t''", &[(TokenType::TSTRING_START, "t'"), (TokenType::TSTRING_END, "'")])]
+#[case(
+    "Rt'''rye {bread!r:>{width}}'''",
+    &[
+        (TokenType::TSTRING_START, "Rt'''"),
+        (TokenType::TSTRING_MIDDLE, "rye "),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "bread"),
+        (TokenType::EXCLAMATION, "!"),
+        (TokenType::NAME, "r"),
+        (TokenType::COLON, ":"),
+        (TokenType::TSTRING_MIDDLE, ">"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "width"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::TSTRING_END, "'''"),
+    ]
+)]
+#[case(
+    "t'{f'{rye}'}'",
+    &[
+        (TokenType::TSTRING_START, "t'"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::FSTRING_START, "f'"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "rye"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::TSTRING_END, "'"),
+    ]
+)]
+fn fstring_tokens(#[case] source: &str, #[case] expected: &[Found]) {
+    let tokens = source_to_tokens(source);
+    let found: Vec<Found> = tokens
+        .iter()
+        .take_while(|token| token.token_type != TokenType::NEWLINE)
+        .map(|token| match token.token_type {
+            TokenType::OP => (token.exact_token_type, token.token_contents.as_str()),
+            _ => (token.token_type, token.token_contents.as_str()),
+        })
+        .collect();
+    assert_eq!(found, expected);
+}
+
+#[rstest]
+#[case("f'rye", (1, 0))]
+#[case("f'rye\n'", (1, 0))]
+#[case("rye = f\"\"\"bread", (1, 6))]
+#[case("f'{rye'", (1, 0))]
+#[case("f'{rye:bread'", (1, 0))]
+#[case("f'{rye}' + f\"{bread\"", (1, 11))]
+#[case("t'rye", (1, 0))]
+fn unterminated_fstring(#[case] source: &str, #[case] start: (usize, usize)) {
+    let error = TokenStream::new(source)
+        .find_map(|token| token.err())
+        .unwrap();
+    assert_eq!(error, TokenizeError::UnterminatedFString { start });
+}
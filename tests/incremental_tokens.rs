@@ -0,0 +1,165 @@
+use rstest::*;
+
+use rye::incremental::{retokenize, TextEdit};
+use rye::tokenize::{TokenizeError, TokenizerOptions};
+use rye::tokens::TokenType;
+
+pub mod common;
+use common::tokenize;
+
+const SOURCE: &str = "import rye
+
+def bread(cheese):
+    if cheese:
+        return '''toast
+'''
+    return (cheese,
+            1)
+
+\u{20000} = bread(2)
+";
+
+/// apply the edit to the old source as an editor would
+fn edited(source: &str, edit: &TextEdit) -> String {
+    let mut edited = String::from(&source[..edit.range.start]);
+    edited.push_str(edit.replacement);
+    edited.push_str(&source[edit.range.end..]);
+    edited
+}
+
+#[rstest]
+#[case(SOURCE, 7..10, "bread")]
+#[case(SOURCE, 0..0, "    ")]
+#[case(SOURCE, 47..47, "\n    ")]
+#[case(SOURCE, 61..73, "'rye'")]
+#[case(SOURCE, 65..68, "")]
+#[case(SOURCE, 85..85, ")")]
+#[case(SOURCE, 85..86, "[")]
+#[case(SOURCE, 118..118, "x = (\n")]
+#[case(SOURCE, 110..110, "if rye:\n  pass\n")]
+#[case(SOURCE, 124..125, "")]
+#[case(SOURCE, 0..126, "")]
+#[case(SOURCE, 126..126, "\u{E9}")]
+#[case("if x:\n  a\nb", 9..11, "")]
+#[case("(a = 1\n", 4..7, "")]
+#[case("      a\n  a\n\r\n  a\n", 5..10, "")]
+fn same_as_tokenizing_again(
+    #[case] old_source: &str,
+    #[case] range: std::ops::Range<usize>,
+    #[case] replacement: &str,
+) {
+    let options = TokenizerOptions {
+        recover: true,
+        trivia: true,
+        ..TokenizerOptions::default()
+    };
+    let edit = TextEdit { range, replacement };
+    let source = edited(old_source, &edit);
+    let mut tokens = tokenize(old_source, options);
+    retokenize(&source, &mut tokens, &edit, options).unwrap();
+    assert_eq!(tokens, tokenize(&source, options));
+}
+
+#[test]
+fn dedent_at_the_end_is_kept() {
+    let options = TokenizerOptions::default();
+    let source = "if x:\n  a\nb";
+    let edit = TextEdit {
+        range: 9..11,
+        replacement: "",
+    };
+    let new_source = edited(source, &edit);
+    let mut tokens = tokenize(source, options);
+    retokenize(&new_source, &mut tokens, &edit, options).unwrap();
+    assert_eq!(tokens, tokenize(&new_source, options));
+    assert_eq!(tokens[tokens.len() - 2].token_type, TokenType::DEDENT);
+}
+
+#[test]
+fn closing_a_docstring_while_recovering() {
+    let options = TokenizerOptions {
+        recover: true,
+        ..TokenizerOptions::default()
+    };
+    let source = "x = \"\"\"doc\nmore\ny = 1\n";
+    let edit = TextEdit {
+        range: 13..13,
+        replacement: "\"\"\"",
+    };
+    let new_source = edited(source, &edit);
+    let mut tokens = tokenize(source, options);
+    assert_eq!(tokens[2].token_type, TokenType::ERRORTOKEN);
+    retokenize(&new_source, &mut tokens, &edit, options).unwrap();
+    assert_eq!(tokens, tokenize(&new_source, options));
+    assert_eq!(tokens[2].token_type, TokenType::STRING);
+}
+
+#[test]
+fn only_the_edited_line_is_relexed() {
+    let options = TokenizerOptions::default();
+    let edit = TextEdit {
+        range: 7..10,
+        replacement: "bread",
+    };
+    let source = edited(SOURCE, &edit);
+    let mut tokens = tokenize(SOURCE, options);
+    let retokenized = retokenize(&source, &mut tokens, &edit, options).unwrap();
+    assert_eq!(retokenized.relexed, 0..3);
+    assert_eq!(
+        tokens[retokenized.relexed]
+            .iter()
+            .map(|token| token.token_type)
+            .collect::<Vec<TokenType>>(),
+        vec![TokenType::NAME, TokenType::NAME, TokenType::NEWLINE]
+    );
+}
+
+#[test]
+fn edited_indent_is_relexed_to_the_end_of_the_block() {
+    let options = TokenizerOptions::default();
+    let source = "if rye:\n    bread\n    cheese\ntoast\n";
+    // dedent the last line of the block
+    let edit = TextEdit {
+        range: 18..22,
+        replacement: "",
+    };
+    let new_source = edited(source, &edit);
+    let mut tokens = tokenize(source, options);
+    let retokenized = retokenize(&new_source, &mut tokens, &edit, options).unwrap();
+    assert_eq!(tokens, tokenize(&new_source, options));
+    assert_eq!(retokenized.relexed, 4..12);
+}
+
+#[test]
+fn errors_leave_tokens_unchanged() {
+    let options = TokenizerOptions::default();
+    let edit = TextEdit {
+        range: 0..0,
+        replacement: "(",
+    };
+    let source = edited(SOURCE, &edit);
+    let mut tokens = tokenize(SOURCE, options);
+    assert_eq!(
+        retokenize(&source, &mut tokens, &edit, options),
+        Err(TokenizeError::EofInMultiLineStatement { start: (1, 0) })
+    );
+    assert_eq!(tokens, tokenize(SOURCE, options));
+}
+
+#[test]
+fn async_def_is_known_after_restarting() {
+    let options = TokenizerOptions {
+        python_version: (3, 6),
+        ..TokenizerOptions::default()
+    };
+    let source = "async def rye():\n    await bread\n    cheese\n";
+    let edit = TextEdit {
+        range: 37..37,
+        replacement: "await ",
+    };
+    let new_source = edited(source, &edit);
+    let mut tokens = tokenize(source, options);
+    let retokenized = retokenize(&new_source, &mut tokens, &edit, options).unwrap();
+    assert_eq!(tokens, tokenize(&new_source, options));
+    assert_eq!(tokens[retokenized.relexed][0].token_type, TokenType::AWAIT);
+}
@@ -0,0 +1,200 @@
+use rstest::*;
+
+use rye::tokenize::{completeness, Completeness, TokenStream, TokenizeError, TokenizerOptions};
+use rye::tokens::{Token, TokenType};
+
+fn single_input() -> TokenizerOptions {
+    TokenizerOptions {
+        single_input: true,
+        ..TokenizerOptions::default()
+    }
+}
+
+fn token_types(source: &str, options: TokenizerOptions) -> Vec<TokenType> {
+    TokenStream::with_options(source, options)
+        .map(|token| token.unwrap().token_type)
+        .collect()
+}
+
+#[rstest]
+#[case("\n", vec![TokenType::NEWLINE])]
+#[case("\r\n", vec![TokenType::NEWLINE])]
+#[case("  \n", vec![TokenType::NL])]
+#[case("# rye\n", vec![TokenType::COMMENT, TokenType::NL])]
+#[case(
+    "if rye:\n    bread\n\ntoast\n",
+    vec![
+        TokenType::NAME,
+        TokenType::NAME,
+        TokenType::OP,
+        TokenType::NEWLINE,
+        TokenType::INDENT,
+        TokenType::NAME,
+        TokenType::NEWLINE,
+        TokenType::DEDENT,
+        TokenType::NEWLINE,
+        TokenType::NAME,
+        TokenType::NEWLINE,
+    ]
+)]
+#[case(
+    "if rye:\n    bread\n    \n    toast\n",
+    vec![
+        TokenType::NAME,
+        TokenType::NAME,
+        TokenType::OP,
+        TokenType::NEWLINE,
+        TokenType::INDENT,
+        TokenType::NAME,
+        TokenType::NEWLINE,
+        TokenType::NL,
+        TokenType::NAME,
+        TokenType::NEWLINE,
+        TokenType::DEDENT,
+    ]
+)]
+#[case(
+    "rye = (\n\n1)\n",
+    vec![
+        TokenType::NAME,
+        TokenType::OP,
+        TokenType::OP,
+        TokenType::NL,
+        TokenType::NL,
+        TokenType::NUMBER,
+        TokenType::OP,
+        TokenType::NEWLINE,
+    ]
+)]
+#[case(
+    "rye = '''\n\n'''\n",
+    vec![TokenType::NAME, TokenType::OP, TokenType::STRING, TokenType::NEWLINE]
+)]
+fn single_input_tokens(#[case] source: &str, #[case] mut expected: Vec<TokenType>) {
+    expected.push(TokenType::ENDMARKER);
+    assert_eq!(token_types(source, single_input()), expected);
+}
+
+#[test]
+fn empty_line_ends_blocks() {
+    let tokens = TokenStream::with_options("if rye:\n  if bread:\n    toast\n\n", single_input())
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    let Token {
+        token_type,
+        token_contents,
+        start,
+        end,
+        ..
+    } = &tokens[tokens.len() - 2];
+    assert_eq!(*token_type, TokenType::NEWLINE);
+    assert_eq!(token_contents, "\n");
+    assert_eq!((*start, *end), ((4, 0), (4, 1)));
+    for dedent in &tokens[tokens.len() - 4..tokens.len() - 2] {
+        assert_eq!(dedent.token_type, TokenType::DEDENT);
+        assert_eq!((dedent.start, dedent.end), ((4, 0), (4, 0)));
+    }
+}
+
+// every case agrees with codeop.compile_command(source, symbol="single")
+#[rstest]
+#[case("", Completeness::Complete)]
+#[case("\n", Completeness::Complete)]
+#[case("# rye\n", Completeness::Complete)]
+#[case("rye = 1", Completeness::Complete)]
+#[case("rye = 1\n", Completeness::Complete)]
+#[case("rye = (", Completeness::Incomplete)]
+#[case("rye = (1,\n", Completeness::Incomplete)]
+#[case("rye = [1,\n2]\n", Completeness::Complete)]
+#[case("rye = {1:", Completeness::Incomplete)]
+#[case("if rye:", Completeness::Incomplete)]
+#[case("if rye:\n", Completeness::Incomplete)]
+#[case("if rye:  # bread\n", Completeness::Incomplete)]
+#[case("if rye:\n    bread = 1\n", Completeness::Complete)]
+#[case("if rye:\n    bread = 1", Completeness::Incomplete)]
+#[case("if rye:\n    bread = 1\n\n", Completeness::Complete)]
+#[case("if rye:\n\n", Completeness::Incomplete)]
+#[case("if rye: bread = 1\n", Completeness::Complete)]
+#[case("if rye: bread = 1", Completeness::Incomplete)]
+#[case("if rye: bread = 1  # toast", Completeness::Incomplete)]
+#[case("def rye(): return 1", Completeness::Incomplete)]
+#[case("def rye(): return 1\n", Completeness::Complete)]
+#[case("class Rye: pass", Completeness::Incomplete)]
+#[case("\nclass Rye: pass", Completeness::Incomplete)]
+#[case("\nclass Rye: pass\n", Completeness::Complete)]
+#[case("async def rye(): pass", Completeness::Incomplete)]
+#[case("if rye: bread\nelse: toast", Completeness::Incomplete)]
+#[case("@rye\ndef bread(): pass", Completeness::Incomplete)]
+#[case("@rye\ndef bread(): pass\n", Completeness::Complete)]
+#[case("@rye\n", Completeness::Incomplete)]
+#[case("@rye\n\n", Completeness::Incomplete)]
+#[case("@rye\n@bread\n", Completeness::Incomplete)]
+#[case("@rye\nclass Bread: pass\n", Completeness::Complete)]
+#[case("match(rye)", Completeness::Complete)]
+#[case("rye = 1; bread = 2", Completeness::Complete)]
+#[case("rye = '''\n", Completeness::Incomplete)]
+#[case("rye = '''bread", Completeness::Incomplete)]
+#[case("rye = '''bread'''\n", Completeness::Complete)]
+#[case("rye = 1 + \\", Completeness::Incomplete)]
+#[case("rye = 1 \\\n\n", Completeness::Complete)]
+#[case("rye = 1 \\\n", Completeness::Incomplete)]
+#[case("\\\n", Completeness::Incomplete)]
+#[case("rye = 'bread", Completeness::Invalid)]
+#[case("rye = )\n", Completeness::Invalid)]
+#[case("rye = (]\n", Completeness::Invalid)]
+#[case("rye = f'''{\n", Completeness::Incomplete)]
+#[case("rye = f'''bread\n", Completeness::Incomplete)]
+#[case("rye = f'bread\n", Completeness::Invalid)]
+#[case("rye = f'{bread +", Completeness::Incomplete)]
+#[case("print(f'{rye,", Completeness::Incomplete)]
+#[case("rye = f\"{", Completeness::Incomplete)]
+#[case("rye = f'{bread:>", Completeness::Incomplete)]
+#[case("rye = f'{bread}", Completeness::Invalid)]
+#[case("rye = f'{f\"bread", Completeness::Invalid)]
+#[case("rye = 1 $ 2\n", Completeness::Invalid)]
+#[case(
+    "class Rye:\n    def bread(self):\n        pass",
+    Completeness::Incomplete
+)]
+#[case(
+    "class Rye:\n    def bread(self):\n        pass\n",
+    Completeness::Complete
+)]
+#[case("if rye:\n    bread = 1\n  toast = 2\n", Completeness::Invalid)]
+#[case("if rye:\r\n    bread = 1\r\n", Completeness::Complete)]
+#[case("rye = '''\n\n'''\n", Completeness::Complete)]
+#[case("rye = 01\n", Completeness::Invalid)]
+#[case("for rye in bread:\n    pass\n    \n", Completeness::Complete)]
+fn statement_completeness(#[case] source: &str, #[case] expected: Completeness) {
+    assert_eq!(completeness(source, TokenizerOptions::default()), expected);
+}
+
+#[rstest]
+#[case("rye = (\n", Completeness::Incomplete)]
+#[case("rye = )\n", Completeness::Invalid)]
+#[case("rye = $\n", Completeness::Invalid)]
+fn completeness_ignores_options(#[case] source: &str, #[case] expected: Completeness) {
+    let options = TokenizerOptions {
+        cpython_compat: true,
+        recover: true,
+        ..TokenizerOptions::default()
+    };
+    assert_eq!(completeness(source, options), expected);
+}
+
+#[rstest]
+#[case("rye = t'{bread}'\n", (3, 13), Completeness::Invalid)]
+#[case("rye = t'{bread}'\n", (3, 14), Completeness::Complete)]
+#[case("rye = f'''{bread}\n", (3, 11), Completeness::Incomplete)]
+#[case("rye = f'''{bread}\n", (3, 12), Completeness::Incomplete)]
+fn completeness_by_version(
+    #[case] source: &str,
+    #[case] python_version: (u8, u8),
+    #[case] expected: Completeness,
+) {
+    let options = TokenizerOptions {
+        python_version,
+        ..TokenizerOptions::default()
+    };
+    assert_eq!(completeness(source, options), expected);
+}
@@ -0,0 +1,70 @@
+use rstest::*;
+
+use rye::keywords::{classify_name, NameKind};
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
+use rye::tokens::Token;
+
+#[rstest]
+#[case("if", (3, 14), NameKind::Keyword)]
+#[case("None", (3, 0), NameKind::Keyword)]
+#[case("async", (3, 6), NameKind::SoftKeyword)]
+#[case("async", (3, 7), NameKind::Keyword)]
+#[case("await", (3, 4), NameKind::Identifier)]
+#[case("match", (3, 9), NameKind::Identifier)]
+#[case("match", (3, 10), NameKind::SoftKeyword)]
+#[case("_", (3, 10), NameKind::SoftKeyword)]
+#[case("type", (3, 11), NameKind::Builtin)]
+#[case("type", (3, 12), NameKind::SoftKeyword)]
+#[case("__peg_parser__", (3, 9), NameKind::Keyword)]
+#[case("__peg_parser__", (3, 10), NameKind::Identifier)]
+#[case("len", (3, 0), NameKind::Builtin)]
+#[case("breakpoint", (3, 6), NameKind::Identifier)]
+#[case("breakpoint", (3, 7), NameKind::Builtin)]
+#[case("ExceptionGroup", (3, 11), NameKind::Builtin)]
+#[case("__import__", (3, 14), NameKind::Builtin)]
+#[case("If", (3, 14), NameKind::Identifier)]
+#[case("rye", (3, 14), NameKind::Identifier)]
+fn classified_names(#[case] name: &str, #[case] python_version: (u8, u8), #[case] kind: NameKind) {
+    assert_eq!(classify_name(name, python_version), kind);
+}
+
+#[test]
+fn classified_tokens() {
+    let options = TokenizerOptions {
+        python_version: (3, 6),
+        ..TokenizerOptions::default()
+    };
+    let found: Vec<Option<NameKind>> =
+        TokenStream::with_options("async def rye(): await print(match)", options)
+            .collect::<Result<Vec<Token>, TokenizeError>>()
+            .unwrap()
+            .iter()
+            .take(9)
+            .map(|token| token.name_kind((3, 6)))
+            .collect();
+    assert_eq!(
+        found,
+        vec![
+            Some(NameKind::SoftKeyword),
+            Some(NameKind::Keyword),
+            Some(NameKind::Identifier),
+            None,
+            None,
+            None,
+            Some(NameKind::SoftKeyword),
+            Some(NameKind::Builtin),
+            None,
+        ]
+    );
+}
+
+#[rstest]
+#[case("\u{2113}en", Some(NameKind::Builtin))]
+#[case("\u{FF49}\u{FF46}", Some(NameKind::Identifier))]
+#[case("\u{FB01}le", Some(NameKind::Identifier))]
+fn classified_normal_forms(#[case] source: &str, #[case] kind: Option<NameKind>) {
+    let tokens = TokenStream::new(source)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    assert_eq!(tokens[0].name_kind(TokenizerOptions::LATEST_VERSION), kind);
+}
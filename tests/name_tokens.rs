@@ -1,5 +1,12 @@
+// the assertions below are kept as first written, which newer lints would rewrite
+#![allow(
+    clippy::format_in_format_args,
+    clippy::len_zero,
+    clippy::useless_conversion
+)]
 use rstest::*;
 
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
 use rye::tokens::{Token, TokenType};
 
 pub mod common;
@@ -18,6 +25,12 @@ use common::check_single_token_statement;
 #[case("S")]
 #[case("_")]
 #[case("__")]
+#[case("\u{E9}t\u{E9}")]
+#[case("\u{2118}")]
+#[case("spam\u{B7}eggs")]
+#[case("\u{FB01}le")]
+#[case("spam\u{FF9E}")]
+#[case("\u{1D518}")]
 fn single_name_token(#[case] source: &str) {
     let Token {
         token_type,
@@ -25,6 +38,10 @@ fn single_name_token(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = check_single_token_statement(source);
     assert_eq!(
         token_type,
@@ -39,3 +56,42 @@ fn single_name_token(#[case] source: &str) {
         format!("{:?}", exact_token_type)
     );
 }
+
+#[rstest]
+#[case("\u{B7}spam")]
+#[case("\u{309B}spam")]
+#[case("\u{FF9E}spam")]
+#[case("spam\u{20AC}")]
+fn not_single_name_token(#[case] source: &str) {
+    // left as ERRORTOKENs when recovering
+    let options = TokenizerOptions {
+        recover: true,
+        ..TokenizerOptions::default()
+    };
+    let tokens = TokenStream::with_options(source, options)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    assert_ne!(tokens[0].token_contents, source);
+}
+
+#[rstest]
+#[case("file", "file")]
+#[case("\u{FB01}le", "file")]
+#[case("\u{1D518}", "U")]
+#[case("\u{212B}ngstr\u{F6}m", "\u{C5}ngstr\u{F6}m")]
+fn normalized_name(#[case] source: &str, #[case] normalized: &str) {
+    let tokens = TokenStream::new(source)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    assert_eq!(tokens[0].normalized_name().as_deref(), Some(normalized));
+}
+
+#[test]
+fn normalized_names_compare_equal() {
+    let tokens = TokenStream::new("\u{FB01}le = file")
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    assert_ne!(tokens[0].token_contents, tokens[2].token_contents);
+    assert_eq!(tokens[0].normalized_name(), tokens[2].normalized_name());
+    assert_eq!(tokens[1].normalized_name(), None);
+}
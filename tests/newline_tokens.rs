@@ -1,3 +1,9 @@
+// the assertions below are kept as first written, which newer lints would rewrite
+#![allow(
+    clippy::format_in_format_args,
+    clippy::len_zero,
+    clippy::useless_conversion
+)]
 use rstest::*;
 
 use rye::tokens::{Token, TokenType};
@@ -9,8 +15,8 @@ use common::source_to_tokens;
 #[case("\n")]
 #[case("    \n")]
 #[case("\n\t")]
-//#[case("\r")]
-//#[case("\r\n")]
+#[case("\r")]
+#[case("\r\n")]
 fn insignificant_newlines(#[case] source: &str) {
     let mut tokens = source_to_tokens(source);
     let Token {
@@ -19,6 +25,10 @@ fn insignificant_newlines(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = tokens.pop().unwrap();
     assert_eq!(
         token_type,
@@ -45,6 +55,10 @@ fn significant_newline_token(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = tokens.pop().unwrap();
     assert_eq!(
         token_type,
@@ -74,6 +88,10 @@ fn logical_lines(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } in tokens.iter()
     {
         if *token_type == TokenType::NEWLINE {
@@ -86,3 +104,43 @@ fn logical_lines(#[case] source: &str) {
         "Multiple NEWLINEs found. Was expecting all other newline characters to be NL"
     );
 }
+
+#[rstest]
+#[case("\n")]
+#[case("\r\n")]
+#[case("\r")]
+fn universal_newlines(#[case] newline: &str) {
+    let source = format!(
+        "rye = 'one'{nl}# two{nl}bread = \\{nl}'thr\\{nl}ee'{nl}",
+        nl = newline
+    );
+    let tokens = source_to_tokens(&source);
+    let found: Vec<_> = tokens
+        .iter()
+        .map(|token| {
+            (
+                token.token_type,
+                token.token_contents.as_str(),
+                token.start,
+                token.end,
+            )
+        })
+        .collect();
+    let nl_size = newline.chars().count();
+    let string = format!("'thr\\{}ee'", newline);
+    assert_eq!(
+        found,
+        vec![
+            (TokenType::NAME, "rye", (1, 0), (1, 3)),
+            (TokenType::OP, "=", (1, 4), (1, 5)),
+            (TokenType::STRING, "'one'", (1, 6), (1, 11)),
+            (TokenType::NEWLINE, newline, (1, 11), (1, 11 + nl_size)),
+            (TokenType::COMMENT, "# two", (2, 0), (2, 5)),
+            (TokenType::NL, newline, (2, 5), (2, 5 + nl_size)),
+            (TokenType::NAME, "bread", (3, 0), (3, 5)),
+            (TokenType::OP, "=", (3, 6), (3, 7)),
+            (TokenType::STRING, string.as_str(), (4, 0), (5, 3)),
+            (TokenType::NEWLINE, newline, (5, 3), (5, 3 + nl_size)),
+        ]
+    );
+}
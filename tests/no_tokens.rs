@@ -1,3 +1,9 @@
+// the assertions below are kept as first written, which newer lints would rewrite
+#![allow(
+    clippy::format_in_format_args,
+    clippy::len_zero,
+    clippy::useless_conversion
+)]
 use rstest::*;
 
 use rye::tokens::{Token, TokenType};
@@ -80,6 +86,10 @@ fn explicit_line_join(#[case] source: &str, #[case] is_statement: bool) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = tokens.pop().unwrap();
     assert_eq!(
         token_type,
@@ -103,6 +113,10 @@ fn explicit_line_join(#[case] source: &str, #[case] is_statement: bool) {
             token_contents: _,
             col_start: _,
             col_end: _,
+            byte_start: _,
+            byte_end: _,
+            start: _,
+            end: _,
         } = token;
         assert_ne!(token_type, TokenType::NL, "Got extra NL Token",);
         assert_ne!(token_type, TokenType::NEWLINE, "Got extra NEWLINE Token",);
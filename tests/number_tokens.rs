@@ -1,9 +1,11 @@
 use rstest::*;
 
+use rye::numbers::NumberValue;
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
 use rye::tokens::{Token, TokenType};
 
 pub mod common;
-use common::{check_single_token_statement, source_to_tokens};
+use common::{check_single_token_statement, recovered_tokens, source_to_tokens};
 
 #[rstest]
 #[case("1")]
@@ -21,18 +23,22 @@ fn single_intiger_token(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = check_single_token_statement(source);
     assert_eq!(
         token_type,
         TokenType::NUMBER,
-        "Symbol Token not of type NUMBER, got type {}",
-        format!("{:?}", token_type)
+        "Symbol Token not of type NUMBER, got type {:?}",
+        token_type
     );
     assert_eq!(
         exact_token_type,
         TokenType::INTEGER,
-        "Symbol Token not of exact type INTEGER, got type {}",
-        format!("{:?}", exact_token_type)
+        "Symbol Token not of exact type INTEGER, got type {:?}",
+        exact_token_type
     );
 }
 
@@ -51,18 +57,22 @@ fn single_binint_token(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = check_single_token_statement(source);
     assert_eq!(
         token_type,
         TokenType::NUMBER,
-        "Symbol Token not of type NUMBER, got type {}",
-        format!("{:?}", token_type)
+        "Symbol Token not of type NUMBER, got type {:?}",
+        token_type
     );
     assert_eq!(
         exact_token_type,
         TokenType::BININT,
-        "Symbol Token not of exact type BININT, got type {}",
-        format!("{:?}", exact_token_type)
+        "Symbol Token not of exact type BININT, got type {:?}",
+        exact_token_type
     );
 }
 
@@ -81,18 +91,22 @@ fn single_octint_token(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = check_single_token_statement(source);
     assert_eq!(
         token_type,
         TokenType::NUMBER,
-        "Symbol Token not of type NUMBER, got type {}",
-        format!("{:?}", token_type)
+        "Symbol Token not of type NUMBER, got type {:?}",
+        token_type
     );
     assert_eq!(
         exact_token_type,
         TokenType::OCTINT,
-        "Symbol Token not of exact type OCTINT, got type {}",
-        format!("{:?}", exact_token_type)
+        "Symbol Token not of exact type OCTINT, got type {:?}",
+        exact_token_type
     );
 }
 
@@ -113,18 +127,22 @@ fn single_hexint_token(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = check_single_token_statement(source);
     assert_eq!(
         token_type,
         TokenType::NUMBER,
-        "Symbol Token not of type NUMBER, got type {}",
-        format!("{:?}", token_type)
+        "Symbol Token not of type NUMBER, got type {:?}",
+        token_type
     );
     assert_eq!(
         exact_token_type,
         TokenType::HEXINT,
-        "Symbol Token not of exact type HEXINT, got type {}",
-        format!("{:?}", exact_token_type)
+        "Symbol Token not of exact type HEXINT, got type {:?}",
+        exact_token_type
     );
 }
 
@@ -165,18 +183,22 @@ fn single_float_token(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = check_single_token_statement(source);
     assert_eq!(
         token_type,
         TokenType::NUMBER,
-        "Symbol Token not of type NUMBER, got type {}",
-        format!("{:?}", token_type)
+        "Symbol Token not of type NUMBER, got type {:?}",
+        token_type
     );
     assert_eq!(
         exact_token_type,
         TokenType::FLOAT,
-        "Symbol Token not of exact type FLOAT, got type {}",
-        format!("{:?}", exact_token_type)
+        "Symbol Token not of exact type FLOAT, got type {:?}",
+        exact_token_type
     );
 }
 
@@ -196,21 +218,71 @@ fn single_imaginary_token(#[case] source: &str) {
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = check_single_token_statement(source);
     assert_eq!(
         token_type,
         TokenType::NUMBER,
-        "Symbol Token not of type NUMBER, got type {}",
-        format!("{:?}", token_type)
+        "Symbol Token not of type NUMBER, got type {:?}",
+        token_type
     );
     assert_eq!(
         exact_token_type,
         TokenType::IMAGINARY,
-        "Symbol Token not of exact type IMAGINARY, got type {}",
-        format!("{:?}", exact_token_type)
+        "Symbol Token not of exact type IMAGINARY, got type {:?}",
+        exact_token_type
     );
 }
 
+/// the tokens of a source whose first `split` chars are a number and the rest a single token
+fn runon_tokens(
+    source: &str,
+    split: usize,
+    exact_number: TokenType,
+    runon_type: TokenType,
+    runon_exact: TokenType,
+) -> Vec<Token> {
+    let size = source.chars().count();
+    vec![
+        Token {
+            token_type: TokenType::NUMBER,
+            exact_token_type: exact_number,
+            token_contents: source[..split].to_string(),
+            col_start: 0,
+            col_end: split,
+            byte_start: 0,
+            byte_end: split,
+            start: (1, 0),
+            end: (1, split),
+        },
+        Token {
+            token_type: runon_type,
+            exact_token_type: runon_exact,
+            token_contents: source[split..].to_string(),
+            col_start: split,
+            col_end: size,
+            byte_start: split,
+            byte_end: size,
+            start: (1, split),
+            end: (1, size),
+        },
+        Token {
+            token_type: TokenType::NEWLINE,
+            exact_token_type: TokenType::NEWLINE,
+            token_contents: String::from(""),
+            col_start: size,
+            col_end: (size + 1),
+            byte_start: size,
+            byte_end: size,
+            start: (1, size),
+            end: (1, (size + 1)),
+        },
+    ]
+}
+
 #[rstest]
 #[case("123_4_", 5, TokenType::INTEGER, TokenType::NAME, TokenType::NAME)]
 #[case("0_", 1, TokenType::INTEGER, TokenType::NAME, TokenType::NAME)]
@@ -220,9 +292,8 @@ fn single_imaginary_token(#[case] source: &str) {
 #[case("000e", 3, TokenType::INTEGER, TokenType::NAME, TokenType::NAME)]
 #[case("123__4", 3, TokenType::INTEGER, TokenType::NAME, TokenType::NAME)]
 #[case("123eyr", 3, TokenType::INTEGER, TokenType::NAME, TokenType::NAME)]
-#[case("000123", 3, TokenType::INTEGER, TokenType::NUMBER, TokenType::INTEGER)]
-#[case("010234", 1, TokenType::INTEGER, TokenType::NUMBER, TokenType::INTEGER)]
-#[case("0_12_3", 1, TokenType::INTEGER, TokenType::NAME, TokenType::NAME)]
+#[case("0x_", 1, TokenType::INTEGER, TokenType::NAME, TokenType::NAME)]
+#[case("0b_2", 1, TokenType::INTEGER, TokenType::NAME, TokenType::NAME)]
 #[case("123.e", 4, TokenType::FLOAT, TokenType::NAME, TokenType::NAME)]
 #[case("12jeep", 3, TokenType::IMAGINARY, TokenType::NAME, TokenType::NAME)]
 fn runon_number_tokens(
@@ -232,40 +303,46 @@ fn runon_number_tokens(
     #[case] runon_type: TokenType,
     #[case] runon_exact: TokenType,
 ) {
-    let size = source.chars().count();
     let tokens = source_to_tokens(source);
     assert_eq!(
-        &tokens[..],
-        &[
-            Token {
-                token_type: TokenType::NUMBER,
-                exact_token_type: exact_number,
-                token_contents: source[..split].to_string(),
-                col_start: 0,
-                col_end: split,
-            },
-            Token {
-                token_type: runon_type,
-                exact_token_type: runon_exact,
-                token_contents: source[split..].to_string(),
-                col_start: split,
-                col_end: size,
-            },
-            Token {
-                token_type: TokenType::NEWLINE,
-                exact_token_type: TokenType::NEWLINE,
-                token_contents: String::from(""),
-                col_start: size,
-                col_end: (size + 1),
-            },
-        ]
+        tokens,
+        runon_tokens(source, split, exact_number, runon_type, runon_exact)
+    );
+}
+
+#[rstest]
+#[case("000123", 3, TokenType::NUMBER, TokenType::INTEGER)]
+#[case("010234", 1, TokenType::NUMBER, TokenType::INTEGER)]
+#[case("0_12_3", 1, TokenType::NAME, TokenType::NAME)]
+fn leading_zero_number_tokens(
+    #[case] source: &str,
+    #[case] split: usize,
+    #[case] runon_type: TokenType,
+    #[case] runon_exact: TokenType,
+) {
+    let (mut tokens, diagnostics) = recovered_tokens(source);
+    // the ENDMARKER
+    tokens.pop();
+    assert_eq!(
+        tokens,
+        runon_tokens(source, split, TokenType::INTEGER, runon_type, runon_exact)
+    );
+    assert_eq!(
+        diagnostics,
+        vec![TokenizeError::LeadingZeros { start: (1, 0) }]
     );
 }
 
 #[test]
 fn multiple_runon_number_tokens() {
     let source = "0012eyr";
-    let tokens = source_to_tokens(source);
+    let (mut tokens, diagnostics) = recovered_tokens(source);
+    // the ENDMARKER
+    tokens.pop();
+    assert_eq!(
+        diagnostics,
+        vec![TokenizeError::LeadingZeros { start: (1, 0) }]
+    );
     assert_eq!(
         &tokens[..],
         &[
@@ -275,6 +352,10 @@ fn multiple_runon_number_tokens() {
                 token_contents: String::from("00"),
                 col_start: 0,
                 col_end: 2,
+                byte_start: 0,
+                byte_end: 2,
+                start: (1, 0),
+                end: (1, 2),
             },
             Token {
                 token_type: TokenType::NUMBER,
@@ -282,6 +363,10 @@ fn multiple_runon_number_tokens() {
                 token_contents: String::from("12"),
                 col_start: 2,
                 col_end: 4,
+                byte_start: 2,
+                byte_end: 4,
+                start: (1, 2),
+                end: (1, 4),
             },
             Token {
                 token_type: TokenType::NAME,
@@ -289,6 +374,10 @@ fn multiple_runon_number_tokens() {
                 token_contents: String::from("eyr"),
                 col_start: 4,
                 col_end: 7,
+                byte_start: 4,
+                byte_end: 7,
+                start: (1, 4),
+                end: (1, 7),
             },
             Token {
                 token_type: TokenType::NEWLINE,
@@ -296,7 +385,108 @@ fn multiple_runon_number_tokens() {
                 token_contents: String::from(""),
                 col_start: 7,
                 col_end: 8,
+                byte_start: 7,
+                byte_end: 7,
+                start: (1, 7),
+                end: (1, 8),
             },
         ]
     );
 }
+
+fn integer(digits: &str) -> NumberValue {
+    NumberValue::Integer(digits.parse().unwrap())
+}
+
+#[rstest]
+#[case("0", integer("0"))]
+#[case("0_0", integer("0"))]
+#[case("1_000", integer("1000"))]
+#[case("0b1_0", integer("2"))]
+#[case("0O777", integer("511"))]
+#[case("0x_fF", integer("255"))]
+#[case(
+    "340282366920938463463374607431768211456",
+    integer("340282366920938463463374607431768211456")
+)]
+#[case("0xFFFF_FFFF_FFFF_FFFF_FFFF", integer("1208925819614629174706175"))]
+#[case("9007199254740993", integer("9007199254740993"))]
+#[case("9007199254740993.0", NumberValue::Float(9007199254740992.0))]
+#[case("0.1", NumberValue::Float(0.1))]
+#[case("1_0.2_5e-1_0", NumberValue::Float(10.25e-10))]
+#[case("007e5", NumberValue::Float(700000.0))]
+#[case(".5", NumberValue::Float(0.5))]
+#[case("5.", NumberValue::Float(5.0))]
+#[case("2.5e-324", NumberValue::Float(5e-324))]
+#[case("1e400", NumberValue::Float(f64::INFINITY))]
+#[case("1_000j", NumberValue::Imaginary(1000.0))]
+#[case("007J", NumberValue::Imaginary(7.0))]
+#[case("1.5e3j", NumberValue::Imaginary(1500.0))]
+fn number_value(#[case] source: &str, #[case] expected: NumberValue) {
+    let token = check_single_token_statement(source);
+    assert_eq!(token.number_value(), Some(expected));
+}
+
+#[test]
+fn not_a_number_value() {
+    let tokens = source_to_tokens("rye");
+    assert_eq!(tokens[0].number_value(), None);
+}
+
+#[rstest]
+#[case("007", (1, 0))]
+#[case("rye = 00_7", (1, 6))]
+#[case("0_12_3", (1, 0))]
+#[case("[0, 01]", (1, 4))]
+fn leading_zeros(#[case] source: &str, #[case] start: (usize, usize)) {
+    assert_eq!(
+        TokenStream::new(source).collect::<Result<Vec<Token>, TokenizeError>>(),
+        Err(TokenizeError::LeadingZeros { start })
+    );
+}
+
+#[test]
+fn leading_zeros_message() {
+    let error = TokenizeError::LeadingZeros { start: (1, 0) };
+    assert_eq!(
+        error.to_string(),
+        "leading zeros in decimal integer literals are not permitted; \
+        use an 0o prefix for octal integers (line 1, column 0)"
+    );
+}
+
+#[rstest]
+#[case("007", &["007"])]
+#[case("0_7", &["0_7"])]
+#[case("007else", &["007", "else"])]
+#[case("0_7e", &["0_7", "e"])]
+fn leading_zeros_compat(#[case] source: &str, #[case] expected: &[&str]) {
+    let options = TokenizerOptions {
+        cpython_compat: true,
+        ..TokenizerOptions::default()
+    };
+    let tokens = TokenStream::with_options(source, options)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    let found: Vec<&str> = tokens[..expected.len()]
+        .iter()
+        .map(|token| token.token_contents.as_str())
+        .collect();
+    assert_eq!(found, expected);
+}
+
+#[rstest]
+#[case("007")]
+#[case("0_12_3")]
+fn leading_zeros_compat_value(#[case] source: &str) {
+    let options = TokenizerOptions {
+        cpython_compat: true,
+        ..TokenizerOptions::default()
+    };
+    let token = TokenStream::with_options(source, options)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(token.token_type, TokenType::NUMBER);
+    assert_eq!(token.number_value(), None);
+}
@@ -0,0 +1,97 @@
+use rstest::*;
+
+use rye::offsets::{SourceOffsets, Unit};
+use rye::tokenize::{TokenStream, TokenizeError};
+use rye::tokens::Token;
+
+#[rstest]
+#[case("rye = 'bread'\n")]
+#[case("\u{20000} = '\u{E9}'")]
+#[case("if rye:\r\n    cheese(1.5e3, \"\"\"\u{E9}\n\"\"\") # \u{1F35E}\r")]
+#[case("rye(\n  \u{E9})\n")]
+fn byte_ranges_slice_source(#[case] source: &str) {
+    let tokens = TokenStream::new(source)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    for token in tokens.iter() {
+        let contents = &source[token.byte_start..token.byte_end];
+        if !contents.is_empty() || !token.token_contents.is_empty() {
+            assert_eq!(contents, token.token_contents);
+        };
+    }
+}
+
+#[rstest]
+#[case("rye", 2, Unit::Byte, Unit::Char, Some(2))]
+#[case("rye", 3, Unit::Char, Unit::Utf16, Some(3))]
+#[case("rye", 4, Unit::Char, Unit::Byte, None)]
+#[case("\u{E9}rye", 2, Unit::Byte, Unit::Char, Some(1))]
+#[case("\u{E9}rye", 1, Unit::Byte, Unit::Char, None)]
+#[case("\u{E9}rye", 3, Unit::Char, Unit::Byte, Some(4))]
+#[case("\u{1F980}rye", 1, Unit::Char, Unit::Utf16, Some(2))]
+#[case("\u{1F980}rye", 1, Unit::Utf16, Unit::Char, None)]
+#[case("\u{1F980}rye", 5, Unit::Utf16, Unit::Byte, Some(7))]
+#[case("r\u{1F980}y\u{E9}", 6, Unit::Byte, Unit::Utf16, Some(4))]
+#[case("r\u{1F980}y\u{E9}", 7, Unit::Byte, Unit::Utf16, None)]
+#[case("r\u{1F980}y\u{E9}", 8, Unit::Byte, Unit::Char, Some(4))]
+#[case("r\u{1F980}y\u{E9}", 9, Unit::Byte, Unit::Char, None)]
+fn convert_offset(
+    #[case] source: &str,
+    #[case] offset: usize,
+    #[case] from: Unit,
+    #[case] to: Unit,
+    #[case] converted: Option<usize>,
+) {
+    assert_eq!(
+        SourceOffsets::new(source).convert(offset, from, to),
+        converted
+    );
+}
+
+#[rstest]
+#[case("rye\n\u{1F980} = 1", (2, 1), Unit::Char, Unit::Utf16, Some((2, 2)))]
+#[case("rye\n\u{1F980} = 1", (2, 1), Unit::Char, Unit::Byte, Some((2, 4)))]
+#[case("rye\r\n\u{E9} = 1", (2, 3), Unit::Byte, Unit::Char, Some((2, 2)))]
+#[case("\u{E9}\r\u{E9} = 1", (2, 1), Unit::Char, Unit::Utf16, Some((2, 1)))]
+#[case("rye\nbread", (1, 5), Unit::Char, Unit::Byte, None)]
+#[case("rye\nbread", (3, 0), Unit::Char, Unit::Byte, None)]
+#[case("rye\nbread", (0, 0), Unit::Char, Unit::Byte, None)]
+fn convert_position(
+    #[case] source: &str,
+    #[case] position: (usize, usize),
+    #[case] from: Unit,
+    #[case] to: Unit,
+    #[case] converted: Option<(usize, usize)>,
+) {
+    assert_eq!(
+        SourceOffsets::new(source).convert_position(position, from, to),
+        converted
+    );
+}
+
+#[test]
+fn token_positions_to_utf16() {
+    let source = "\u{20000} = '\u{1F35E}'\n";
+    let offsets = SourceOffsets::new(source);
+    let tokens = TokenStream::new(source)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    let found: Vec<_> = tokens
+        .iter()
+        .take(3)
+        .map(|token| {
+            (
+                offsets.convert_position(token.start, Unit::Char, Unit::Utf16),
+                offsets.convert_position(token.end, Unit::Char, Unit::Utf16),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (Some((1, 0)), Some((1, 2))),
+            (Some((1, 3)), Some((1, 4))),
+            (Some((1, 5)), Some((1, 9))),
+        ]
+    );
+}
@@ -0,0 +1,91 @@
+use rstest::*;
+
+use rye::tokenize::TokenStream;
+use rye::tokens::{Token, TokenType};
+
+pub mod common;
+use common::source_to_tokens;
+
+type Positions = ((usize, usize), (usize, usize));
+
+#[rstest]
+#[case("rye", &[((1, 0), (1, 3)), ((1, 3), (1, 4))])]
+#[case("rye\n", &[((1, 0), (1, 3)), ((1, 3), (1, 4))])]
+#[case("\nrye", &[((1, 0), (1, 1)), ((2, 0), (2, 3)), ((2, 3), (2, 4))])]
+#[case("rye # bread", &[((1, 0), (1, 3)), ((1, 4), (1, 11)), ((1, 11), (1, 12))])]
+#[case(
+    "\"\"\"rye\nbread\"\"\"\n",
+    &[((1, 0), (2, 8)), ((2, 8), (2, 9))]
+)]
+#[case(
+    "rye(\n  bread)",
+    &[((1, 0), (1, 3)), ((1, 3), (1, 4)), ((1, 4), (1, 5)), ((2, 2), (2, 7)), ((2, 7), (2, 8)), ((2, 8), (2, 9))]
+)]
+#[case(
+    "rye \\\n  bread",
+    &[((1, 0), (1, 3)), ((2, 2), (2, 7)), ((2, 7), (2, 8))]
+)]
+#[case(
+    "if rye:\n    bread\n",
+    &[
+        ((1, 0), (1, 2)),
+        ((1, 3), (1, 6)),
+        ((1, 6), (1, 7)),
+        ((1, 7), (1, 8)),
+        ((2, 0), (2, 4)),
+        ((2, 4), (2, 9)),
+        ((2, 9), (2, 10)),
+        ((3, 0), (3, 0)),
+    ]
+)]
+#[case(
+    "if rye:\n    bread\ncheese",
+    &[
+        ((1, 0), (1, 2)),
+        ((1, 3), (1, 6)),
+        ((1, 6), (1, 7)),
+        ((1, 7), (1, 8)),
+        ((2, 0), (2, 4)),
+        ((2, 4), (2, 9)),
+        ((2, 9), (2, 10)),
+        ((3, 0), (3, 0)),
+        ((3, 0), (3, 6)),
+        ((3, 6), (3, 7)),
+    ]
+)]
+#[case("\u{20000} = 'é'", &[((1, 0), (1, 1)), ((1, 2), (1, 3)), ((1, 4), (1, 7)), ((1, 7), (1, 8))])]
+fn token_positions(#[case] source: &str, #[case] positions: &[Positions]) {
+    let tokens = source_to_tokens(source);
+    let found: Vec<Positions> = tokens
+        .iter()
+        .map(|token| (token.start, token.end))
+        .collect();
+    assert_eq!(
+        found, positions,
+        "Unexpected positions for tokens {:?}",
+        tokens
+    );
+}
+
+#[rstest]
+#[case("", (1, 0))]
+#[case("rye", (2, 0))]
+#[case("rye\n", (2, 0))]
+#[case("if rye:\n    bread", (3, 0))]
+#[case("rye\n\n", (3, 0))]
+fn endmarker_position(#[case] source: &str, #[case] position: (usize, usize)) {
+    let Token {
+        token_type,
+        exact_token_type: _,
+        token_contents: _,
+        col_start: _,
+        col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start,
+        end,
+    } = TokenStream::new(source).last().unwrap().unwrap();
+    assert_eq!(token_type, TokenType::ENDMARKER);
+    assert_eq!(start, position, "ENDMARKER did not start at {:?}", position);
+    assert_eq!(end, position, "ENDMARKER did not end at {:?}", position);
+}
@@ -0,0 +1,155 @@
+use std::io::{self, BufReader, Cursor, Read};
+use std::rc::Rc;
+
+use rstest::*;
+
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
+use rye::tokens::Token;
+
+/// read source in reads of a single byte, so every token crosses a read boundary
+fn read_tokens(source: &str, options: TokenizerOptions) -> Result<Vec<Token>, TokenizeError> {
+    let reader = BufReader::with_capacity(1, source.as_bytes());
+    TokenStream::from_reader_with_options(reader, options).collect()
+}
+
+#[rstest]
+#[case("")]
+#[case("rye = 1\n")]
+#[case("rye = 1")]
+#[case("rye = '''\nbread\n\n'''\n")]
+#[case("rye = (\n  1,\n  2\n)\n")]
+#[case("rye = [1,\n  {2:\n\n3}]\n")]
+#[case("if rye:\n    bread = 1\n\n")]
+#[case("rye = 1 + \\\n    2\n")]
+#[case("rye = 1\r\nbread = '''\r\n'''\r\n")]
+#[case("rye = 1\rbread = 2\r")]
+#[case("rye = f'{\nbread!r:>{\nwidth}}'\n")]
+#[case("rye = '\u{E9}\u{20000}'\n# \u{1F35E}\n")]
+fn same_tokens(#[case] source: &str) {
+    let options = TokenizerOptions::default();
+    assert_eq!(
+        read_tokens(source, options),
+        TokenStream::with_options(source, options).collect()
+    );
+}
+
+#[test]
+fn byte_order_mark() {
+    let options = TokenizerOptions::default();
+    assert_eq!(
+        read_tokens("\u{FEFF}rye = 1\n", options),
+        read_tokens("rye = 1\n", options)
+    );
+}
+
+#[test]
+fn reader_kept_on_this_thread() {
+    // like a locked stdin, this reader cannot be sent to another thread
+    let source = "rye = 1\n";
+    let reader = Cursor::new(Rc::<[u8]>::from(source.as_bytes()));
+    assert_eq!(
+        TokenStream::from_reader(reader).collect::<Result<Vec<Token>, TokenizeError>>(),
+        TokenStream::new(source).collect()
+    );
+}
+
+#[test]
+fn coding_cookie_ignored() {
+    // the cookie would decode these bytes as "\u{C3}\u{A9}", but they are read as UTF-8
+    let source = "# coding: latin-1\nrye = '\u{E9}'\n";
+    assert_eq!(
+        read_tokens(source, TokenizerOptions::default()),
+        TokenStream::new(source).collect()
+    );
+}
+
+#[rstest]
+#[case("rye = '''\nbread\n", TokenizeError::UnterminatedTripleQuotedString { start: (1, 6) })]
+#[case("rye = (\n1,\n", TokenizeError::EofInMultiLineStatement { start: (1, 6) })]
+fn same_error(#[case] source: &str, #[case] error: TokenizeError) {
+    assert_eq!(read_tokens(source, TokenizerOptions::default()), Err(error));
+}
+
+#[test]
+fn same_recovered_tokens() {
+    let source = "rye = (\n  $,\n  01\n";
+    let options = TokenizerOptions {
+        recover: true,
+        ..TokenizerOptions::default()
+    };
+    let mut read = TokenStream::from_reader_with_options(source.as_bytes(), options);
+    let mut tokenized = TokenStream::with_options(source, options);
+    assert_eq!(
+        read.by_ref().collect::<Vec<_>>(),
+        tokenized.by_ref().collect::<Vec<_>>()
+    );
+    assert_eq!(read.diagnostics(), tokenized.diagnostics());
+}
+
+#[test]
+fn long_source() {
+    // long enough that tokenized lines are dropped while reading
+    let mut source = String::from("rye = '''\n");
+    for line in 0..20_000 {
+        source.push_str(&format!("bread{}\n", line));
+    }
+    source.push_str("'''\nif rye:\n");
+    for line in 0..20_000 {
+        source.push_str(&format!(
+            "    bread{} = ('\u{E9}',\n        {})\n",
+            line, line
+        ));
+    }
+    let options = TokenizerOptions::default();
+    assert_eq!(
+        read_tokens(&source, options),
+        TokenStream::with_options(&source, options).collect()
+    );
+}
+
+#[rstest]
+#[case(b"rye = 1\nbread = '\xE9'\n", (2, 9))]
+#[case(b"rye = '''\n\xC3\xA9\xFF'''\n", (2, 1))]
+fn undecodable_source(#[case] source: &[u8], #[case] start: (usize, usize)) {
+    let tokens: Vec<_> = TokenStream::from_reader(source).collect();
+    assert_eq!(
+        tokens.last(),
+        Some(&Err(TokenizeError::UndecodableSource {
+            encoding: String::from("utf-8"),
+            start
+        }))
+    );
+}
+
+/// a reader of source that fails after giving all of it
+struct FailingReader<'src> {
+    source: &'src [u8],
+}
+
+impl Read for FailingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.source.is_empty() {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "rye"))
+        } else {
+            self.source.read(buf)
+        }
+    }
+}
+
+#[test]
+fn unreadable_source() {
+    let reader = BufReader::new(FailingReader {
+        source: b"rye = 1\nbread = (\n",
+    });
+    let tokens: Vec<_> = TokenStream::from_reader(reader).collect();
+    let error = TokenizeError::UnreadableSource {
+        message: String::from("rye"),
+        start: (3, 0),
+    };
+    assert_eq!(tokens.last(), Some(&Err(error.clone())));
+    assert!(tokens[..tokens.len() - 1].iter().all(Result::is_ok));
+    assert_eq!(
+        error.to_string(),
+        "source cannot be read: rye (line 3, column 0)"
+    );
+}
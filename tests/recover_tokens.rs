@@ -0,0 +1,181 @@
+use rstest::*;
+
+use rye::tokenize::{TokenStream, TokenizeError};
+use rye::tokens::{Token, TokenType};
+
+pub mod common;
+use common::recovered_tokens;
+
+type Found<'a> = (TokenType, &'a str, (usize, usize));
+
+#[rstest]
+#[case(
+    "rye = '''bread\ncheese = 1\n",
+    &[TokenizeError::UnterminatedTripleQuotedString { start: (1, 6) }]
+)]
+#[case(
+    "if rye:\n    bread\n  cheese\nspam\n",
+    &[TokenizeError::DedentMismatch { start: (3, 2) }]
+)]
+#[case(
+    "rye = (1,\nbread = [2\n",
+    &[TokenizeError::EofInMultiLineStatement { start: (1, 6) }]
+)]
+#[case(
+    "rye = (1,\nbread]\ncheese\n",
+    &[TokenizeError::MismatchedCloser { closer: ']', opener: '(', opener_start: (1, 6), start: (2, 5) }]
+)]
+#[case(
+    "f'{a]}' + 1\nx\n",
+    &[TokenizeError::MismatchedCloser { closer: ']', opener: '{', opener_start: (1, 2), start: (1, 4) }]
+)]
+#[case("rye)\nbread\n", &[TokenizeError::UnbalancedCloser { closer: ')', start: (1, 3) }])]
+#[case("if rye:\n\tbread\n        cheese\n", &[TokenizeError::InconsistentTabs { start: (3, 0) }])]
+#[case("rye = f'{bread\ncheese = 1\n", &[TokenizeError::UnterminatedFString { start: (1, 6) }])]
+#[case(
+    "rye = \u{2018}bread\u{2019}\n",
+    &[
+        TokenizeError::InvalidCharacter { character: '\u{2018}', suggestion: Some('\''), start: (1, 6) },
+        TokenizeError::InvalidCharacter { character: '\u{2019}', suggestion: Some('\''), start: (1, 12) },
+    ]
+)]
+#[case(
+    "rye)\nif bread:\n    cheese\n  spam\n'''",
+    &[
+        TokenizeError::UnbalancedCloser { closer: ')', start: (1, 3) },
+        TokenizeError::DedentMismatch { start: (4, 2) },
+        TokenizeError::UnterminatedTripleQuotedString { start: (5, 0) },
+    ]
+)]
+#[case(
+    "rye = bu'bread'\n",
+    &[TokenizeError::InvalidStringPrefix { prefix: String::from("bu"), start: (1, 6) }]
+)]
+fn whole_source_is_tokenized(#[case] source: &str, #[case] expected: &[TokenizeError]) {
+    let (tokens, diagnostics) = recovered_tokens(source);
+    assert_eq!(diagnostics, expected);
+    assert_eq!(
+        tokens.last().map(|token| token.token_type),
+        Some(TokenType::ENDMARKER)
+    );
+    // every block opened is closed again
+    let indents = tokens
+        .iter()
+        .filter(|token| token.token_type == TokenType::INDENT)
+        .count();
+    let dedents = tokens
+        .iter()
+        .filter(|token| token.token_type == TokenType::DEDENT)
+        .count();
+    assert_eq!(indents, dedents);
+}
+
+#[rstest]
+#[case(
+    "rye = '''bread\n",
+    &[
+        (TokenType::NAME, "rye", (1, 0)),
+        (TokenType::OP, "=", (1, 4)),
+        (TokenType::ERRORTOKEN, "'''", (1, 6)),
+        (TokenType::NAME, "bread", (1, 9)),
+        (TokenType::NEWLINE, "\n", (1, 14)),
+        (TokenType::ENDMARKER, "", (2, 0)),
+    ]
+)]
+#[case(
+    "rb\"\"\"rye",
+    &[
+        (TokenType::ERRORTOKEN, "rb\"\"\"", (1, 0)),
+        (TokenType::NAME, "rye", (1, 5)),
+        (TokenType::NEWLINE, "", (1, 8)),
+        (TokenType::ENDMARKER, "", (2, 0)),
+    ]
+)]
+#[case(
+    "if rye:\n    bread\n  cheese\n",
+    &[
+        (TokenType::NAME, "if", (1, 0)),
+        (TokenType::NAME, "rye", (1, 3)),
+        (TokenType::OP, ":", (1, 6)),
+        (TokenType::NEWLINE, "\n", (1, 7)),
+        (TokenType::INDENT, "    ", (2, 0)),
+        (TokenType::NAME, "bread", (2, 4)),
+        (TokenType::NEWLINE, "\n", (2, 9)),
+        (TokenType::DEDENT, "", (3, 2)),
+        (TokenType::INDENT, "", (3, 2)),
+        (TokenType::NAME, "cheese", (3, 2)),
+        (TokenType::NEWLINE, "\n", (3, 8)),
+        (TokenType::DEDENT, "", (4, 0)),
+        (TokenType::ENDMARKER, "", (4, 0)),
+    ]
+)]
+#[case(
+    "f'{rye'",
+    &[
+        (TokenType::FSTRING_START, "f'", (1, 0)),
+        (TokenType::OP, "{", (1, 2)),
+        (TokenType::NAME, "rye", (1, 3)),
+        (TokenType::ERRORTOKEN, "'", (1, 6)),
+        (TokenType::FSTRING_END, "", (1, 7)),
+        (TokenType::NEWLINE, "", (1, 7)),
+        (TokenType::ENDMARKER, "", (2, 0)),
+    ]
+)]
+#[case(
+    "f'{a]}' + 1\nx\n",
+    &[
+        (TokenType::FSTRING_START, "f'", (1, 0)),
+        (TokenType::OP, "{", (1, 2)),
+        (TokenType::NAME, "a", (1, 3)),
+        (TokenType::OP, "]", (1, 4)),
+        (TokenType::OP, "}", (1, 5)),
+        (TokenType::FSTRING_END, "'", (1, 6)),
+        (TokenType::OP, "+", (1, 8)),
+        (TokenType::NUMBER, "1", (1, 10)),
+        (TokenType::NEWLINE, "\n", (1, 11)),
+        (TokenType::NAME, "x", (2, 0)),
+        (TokenType::NEWLINE, "\n", (2, 1)),
+        (TokenType::ENDMARKER, "", (3, 0)),
+    ]
+)]
+#[case(
+    "rye $ bread",
+    &[
+        (TokenType::NAME, "rye", (1, 0)),
+        (TokenType::ERRORTOKEN, "$", (1, 4)),
+        (TokenType::NAME, "bread", (1, 6)),
+        (TokenType::NEWLINE, "", (1, 11)),
+        (TokenType::ENDMARKER, "", (2, 0)),
+    ]
+)]
+#[case(
+    "ub'rye'",
+    &[
+        (TokenType::NAME, "ub", (1, 0)),
+        (TokenType::STRING, "'rye'", (1, 2)),
+        (TokenType::NEWLINE, "", (1, 7)),
+        (TokenType::ENDMARKER, "", (2, 0)),
+    ]
+)]
+fn recovered_token_stream(#[case] source: &str, #[case] expected: &[Found]) {
+    let (tokens, _) = recovered_tokens(source);
+    let found: Vec<Found> = tokens
+        .iter()
+        .map(|token| (token.token_type, token.token_contents.as_str(), token.start))
+        .collect();
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn errors_without_recovering() {
+    let source = "rye = '''bread\ncheese = 1\n";
+    let mut stream = TokenStream::new(source);
+    let tokens: Vec<Result<Token, TokenizeError>> = stream.by_ref().collect();
+    assert_eq!(
+        tokens.last(),
+        Some(&Err(TokenizeError::UnterminatedTripleQuotedString {
+            start: (1, 6)
+        }))
+    );
+    assert!(stream.diagnostics().is_empty());
+}
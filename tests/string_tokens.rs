@@ -1,5 +1,7 @@
 use rstest::*;
 
+use rye::strings::StringPrefix;
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
 use rye::tokens::{Token, TokenType};
 
 pub mod common;
@@ -33,8 +35,7 @@ use common::check_single_token_statement;
 fn single_always_valid_string(
     #[case] source: &str,
     #[values(
-        "", "f", "F", "r", "R", "u", "U", "b", "B", "rf", "rF", "Rf", "RF", "fr", "Fr", "fR", "FR",
-        "br", "bR", "Br", "BR", "rb", "rB", "Rb", "RB"
+        "", "r", "R", "u", "U", "b", "B", "br", "bR", "Br", "BR", "rb", "rB", "Rb", "RB"
     )]
     prefix: &str,
     #[values("\"", "'", "\"\"\"", "'''")] quotes: &str,
@@ -46,18 +47,22 @@ fn single_always_valid_string(
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = check_single_token_statement(&py_string);
     assert_eq!(
         token_type,
         TokenType::STRING,
-        "Symbol Token not of type STRING, got type {}",
-        format!("{:?}", token_type)
+        "Symbol Token not of type STRING, got type {:?}",
+        token_type
     );
     assert_eq!(
         exact_token_type,
         TokenType::STRING,
-        "Symbol Token not of exact type STRING, got type {}",
-        format!("{:?}", exact_token_type)
+        "Symbol Token not of exact type STRING, got type {:?}",
+        exact_token_type
     );
 }
 
@@ -72,8 +77,7 @@ fn single_always_valid_string(
 fn single_triple_string(
     #[case] source: &str,
     #[values(
-        "", "f", "F", "r", "R", "u", "U", "b", "B", "rf", "rF", "Rf", "RF", "fr", "Fr", "fR", "FR",
-        "br", "bR", "Br", "BR", "rb", "rB", "Rb", "RB"
+        "", "r", "R", "u", "U", "b", "B", "br", "bR", "Br", "BR", "rb", "rB", "Rb", "RB"
     )]
     prefix: &str,
     #[values("\"\"\"", "'''")] quotes: &str,
@@ -85,17 +89,124 @@ fn single_triple_string(
         token_contents: _,
         col_start: _,
         col_end: _,
+        byte_start: _,
+        byte_end: _,
+        start: _,
+        end: _,
     } = check_single_token_statement(&py_string);
     assert_eq!(
         token_type,
         TokenType::STRING,
-        "Symbol Token not of type STRING, got type {}",
-        format!("{:?}", token_type)
+        "Symbol Token not of type STRING, got type {:?}",
+        token_type
     );
     assert_eq!(
         exact_token_type,
         TokenType::STRING,
-        "Symbol Token not of exact type STRING, got type {}",
-        format!("{:?}", exact_token_type)
+        "Symbol Token not of exact type STRING, got type {:?}",
+        exact_token_type
     );
 }
+
+#[rstest]
+#[case("ub''", "ub", (1, 0))]
+#[case("bu''", "bu", (1, 0))]
+#[case("rr''", "rr", (1, 0))]
+#[case("fb''", "fb", (1, 0))]
+#[case("Ur''", "Ur", (1, 0))]
+#[case("tf''", "tf", (1, 0))]
+#[case("rye = rbf\"\"\"bread\"\"\"", "rbf", (1, 6))]
+fn invalid_string_prefix(
+    #[case] source: &str,
+    #[case] prefix: &str,
+    #[case] start: (usize, usize),
+) {
+    assert_eq!(
+        TokenStream::new(source).collect::<Result<Vec<Token>, TokenizeError>>(),
+        Err(TokenizeError::InvalidStringPrefix {
+            prefix: String::from(prefix),
+            start
+        })
+    );
+}
+
+#[test]
+fn invalid_string_prefix_message() {
+    let error = TokenizeError::InvalidStringPrefix {
+        prefix: String::from("ub"),
+        start: (2, 4),
+    };
+    assert_eq!(
+        error.to_string(),
+        "invalid string prefix 'ub' (line 2, column 4)"
+    );
+}
+
+#[test]
+fn invalid_string_prefix_compat() {
+    let options = TokenizerOptions {
+        cpython_compat: true,
+        ..TokenizerOptions::default()
+    };
+    let found: Vec<(TokenType, String)> = TokenStream::with_options("ub'rye'", options)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap()
+        .into_iter()
+        .map(|token| (token.token_type, token.token_contents))
+        .take(2)
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (TokenType::NAME, String::from("ub")),
+            (TokenType::STRING, String::from("'rye'")),
+        ]
+    );
+}
+
+fn prefix(letters: &str) -> StringPrefix {
+    StringPrefix {
+        raw: letters.contains('r'),
+        bytes: letters.contains('b'),
+        format: letters.contains('f'),
+        unicode: letters.contains('u'),
+        template: letters.contains('t'),
+    }
+}
+
+#[rstest]
+#[case("'rye'", Some(prefix("")))]
+#[case("U'rye'", Some(prefix("u")))]
+#[case("Rb'''rye'''", Some(prefix("rb")))]
+#[case("bR\"rye\"", Some(prefix("rb")))]
+#[case("f'{rye}'", Some(prefix("f")))]
+#[case("rF'{rye}'", Some(prefix("rf")))]
+#[case("tr'{rye}'", Some(prefix("rt")))]
+#[case("rye", None)]
+fn string_prefix(#[case] source: &str, #[case] expected: Option<StringPrefix>) {
+    let tokens = TokenStream::new(source)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    assert_eq!(tokens[0].string_prefix(), expected);
+}
+
+#[rstest]
+#[case("u", (3, 2), None)]
+#[case("u", (3, 3), Some(prefix("u")))]
+#[case("br", (3, 2), Some(prefix("rb")))]
+#[case("rb", (3, 2), None)]
+#[case("rb", (3, 3), Some(prefix("rb")))]
+#[case("f", (3, 5), None)]
+#[case("f", (3, 6), Some(prefix("f")))]
+#[case("t", (3, 13), None)]
+#[case("T", (3, 14), Some(prefix("t")))]
+#[case("ur", (3, 14), None)]
+#[case("bt", (3, 14), None)]
+#[case("rye", (3, 14), None)]
+fn parse_string_prefix(
+    #[case] letters: &str,
+    #[case] python_version: (u8, u8),
+    #[case] expected: Option<StringPrefix>,
+) {
+    assert_eq!(StringPrefix::parse(letters, python_version), expected);
+}
@@ -0,0 +1,174 @@
+use rstest::*;
+
+use rye::strings::{DecodeError, DecodedString, EscapeWarning, StringValue, WarningCategory};
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
+use rye::tokens::{Token, TokenRef};
+
+/// evaluate the first token of source as the given version of Python would
+fn string_value(
+    source: &str,
+    python_version: (u8, u8),
+) -> Option<Result<DecodedString, DecodeError>> {
+    let tokens = TokenStream::new(source)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    tokens[0].string_value(python_version)
+}
+
+fn str_value(value: &str) -> StringValue {
+    StringValue::Str(String::from(value))
+}
+
+#[rstest]
+#[case("'rye'", str_value("rye"))]
+#[case("\"\\x41\\N{BULLET}\u{E9}\"", str_value("A\u{2022}\u{E9}"))]
+#[case(
+    "'\\N{latin small letter e with acute}\\N{BACKSPACE}'",
+    str_value("\u{E9}\u{8}")
+)]
+#[case("'\\u00e9\\U0001F35E'", str_value("\u{E9}\u{1F35E}"))]
+#[case("'\\0\\101\\1011'", str_value("\0AA1"))]
+#[case(
+    "'\\a\\b\\f\\n\\r\\t\\v\\\\\\'\\\"'",
+    str_value("\u{7}\u{8}\u{C}\n\r\t\u{B}\\'\"")
+)]
+#[case("'rye\\\nbread'", str_value("ryebread"))]
+#[case("'rye\\\r\nbread'", str_value("ryebread"))]
+#[case("'''rye\r\nbread\r'''", str_value("rye\nbread\n"))]
+#[case("''''rye' \"bread\"'''", str_value("'rye' \"bread\""))]
+#[case(
+    "r'\\x41\\N{BULLET}\\\nrye\\''",
+    str_value("\\x41\\N{BULLET}\\\nrye\\'")
+)]
+#[case("U'\\u00e9'", str_value("\u{E9}"))]
+#[case("b'\\x41\\xff\\101rye'", StringValue::Bytes(vec![0x41, 0xff, 0x41, b'r', b'y', b'e']))]
+#[case("b'\\u00e9'", StringValue::Bytes(b"\\u00e9".to_vec()))]
+#[case("Rb'\\x41'", StringValue::Bytes(b"\\x41".to_vec()))]
+fn decoded_value(#[case] source: &str, #[case] expected: StringValue) {
+    let decoded = string_value(source, (3, 14)).unwrap().unwrap();
+    assert_eq!(decoded.value, expected);
+}
+
+#[rstest]
+#[case("rye")]
+#[case("1")]
+#[case("f'{rye}'")]
+fn not_a_string_value(#[case] source: &str) {
+    assert_eq!(string_value(source, (3, 14)), None);
+}
+
+#[test]
+fn fstring_before_312_has_no_value() {
+    let options = TokenizerOptions {
+        python_version: (3, 11),
+        ..TokenizerOptions::default()
+    };
+    let tokens = TokenStream::with_options("f'rye'", options)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    assert_eq!(tokens[0].string_value((3, 11)), None);
+}
+
+#[rstest]
+#[case("'\\d'", (3, 14), &[(WarningCategory::SyntaxWarning, "\\d", false, (1, 1))])]
+#[case("'\\d'", (3, 11), &[(WarningCategory::DeprecationWarning, "\\d", false, (1, 1))])]
+#[case("'\\d'", (3, 5), &[])]
+#[case("b'\\N{BULLET}'", (3, 12), &[(WarningCategory::SyntaxWarning, "\\N", false, (1, 2))])]
+#[case("'\\400'", (3, 12), &[(WarningCategory::SyntaxWarning, "\\400", true, (1, 1))])]
+#[case("'\\400'", (3, 10), &[])]
+#[case("rye = '''\n  \\[\\]'''", (3, 12), &[(WarningCategory::SyntaxWarning, "\\[", false, (2, 2))])]
+#[case("'\\400\\d'", (3, 12), &[(WarningCategory::SyntaxWarning, "\\400", true, (1, 1))])]
+#[case("'\\é'", (3, 12), &[])]
+#[case("'\\é\\d'", (3, 12), &[(WarningCategory::SyntaxWarning, "\\d", false, (1, 3))])]
+fn escape_warnings(
+    #[case] source: &str,
+    #[case] python_version: (u8, u8),
+    #[case] expected: &[(WarningCategory, &str, bool, (usize, usize))],
+) {
+    let tokens = TokenStream::new(source)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap();
+    let string = tokens
+        .iter()
+        .find(|token| token.string_prefix().is_some())
+        .unwrap();
+    let decoded = string.string_value(python_version).unwrap().unwrap();
+    let expected: Vec<EscapeWarning> = expected
+        .iter()
+        .map(|(category, escape, octal, start)| EscapeWarning {
+            category: *category,
+            escape: String::from(*escape),
+            octal: *octal,
+            start: *start,
+        })
+        .collect();
+    assert_eq!(decoded.warnings, expected);
+}
+
+#[test]
+fn unknown_escapes_are_kept() {
+    let decoded = string_value("'\\d\\400'", (3, 12)).unwrap().unwrap();
+    assert_eq!(decoded.value, str_value("\\d\u{100}"));
+    let decoded = string_value("b'\\400'", (3, 12)).unwrap().unwrap();
+    assert_eq!(decoded.value, StringValue::Bytes(vec![0]));
+}
+
+#[test]
+fn escape_warning_message() {
+    let warning = EscapeWarning {
+        category: WarningCategory::SyntaxWarning,
+        escape: String::from("\\d"),
+        octal: false,
+        start: (1, 1),
+    };
+    assert_eq!(
+        warning.to_string(),
+        "SyntaxWarning: invalid escape sequence '\\d' (line 1, column 1)"
+    );
+}
+
+#[rstest]
+#[case("'\\x4'", DecodeError::TruncatedEscape { escape: 'x', start: (1, 1) })]
+#[case("b'rye\\x'", DecodeError::TruncatedEscape { escape: 'x', start: (1, 5) })]
+#[case("'\\u00e'", DecodeError::TruncatedEscape { escape: 'u', start: (1, 1) })]
+#[case("'\\U0001F35'", DecodeError::TruncatedEscape { escape: 'U', start: (1, 1) })]
+#[case("'\\U00110000'", DecodeError::IllegalCharacter { start: (1, 1) })]
+#[case("'\\ud800'", DecodeError::Surrogate { codepoint: 0xD800, start: (1, 1) })]
+#[case("'\\N'", DecodeError::MalformedCharacterName { start: (1, 1) })]
+#[case("'\\N{}'", DecodeError::MalformedCharacterName { start: (1, 1) })]
+#[case("'\\N{BULLET'", DecodeError::MalformedCharacterName { start: (1, 1) })]
+#[case("'''\n\\N{RYE BREAD}'''", DecodeError::UnknownCharacterName { name: String::from("RYE BREAD"), start: (2, 0) })]
+#[case("b'r\u{E9}'", DecodeError::NonAsciiBytes { start: (1, 3) })]
+#[case("b'\\\u{E9}'", DecodeError::NonAsciiBytes { start: (1, 3) })]
+fn decode_error(#[case] source: &str, #[case] error: DecodeError) {
+    assert_eq!(string_value(source, (3, 14)), Some(Err(error)));
+}
+
+#[test]
+fn decode_error_message() {
+    let error = DecodeError::TruncatedEscape {
+        escape: 'u',
+        start: (3, 4),
+    };
+    assert_eq!(error.start(), (3, 4));
+    assert_eq!(
+        error.to_string(),
+        "truncated \\uXXXX escape (line 3, column 4)"
+    );
+    let error = DecodeError::NonAsciiBytes { start: (1, 2) };
+    assert_eq!(
+        error.to_string(),
+        "bytes can only contain ASCII literal characters (line 1, column 2)"
+    );
+}
+
+#[test]
+fn borrowed_string_value() {
+    let source = "rye = b'\\x00'";
+    let tokens = TokenStream::new(source)
+        .borrowed()
+        .collect::<Result<Vec<TokenRef>, TokenizeError>>()
+        .unwrap();
+    let decoded = tokens[2].string_value((3, 14)).unwrap().unwrap();
+    assert_eq!(decoded.value, StringValue::Bytes(vec![0]));
+}
@@ -0,0 +1,85 @@
+use rstest::*;
+
+use rye::tokenize::TokenizerOptions;
+use rye::tokens::{Token, TokenType};
+
+pub mod common;
+use common::tokenize;
+
+fn trivia() -> TokenizerOptions {
+    TokenizerOptions {
+        trivia: true,
+        ..TokenizerOptions::default()
+    }
+}
+
+fn is_trivia(token: &Token) -> bool {
+    matches!(
+        token.token_type,
+        TokenType::WHITESPACE | TokenType::CONTINUATION
+    )
+}
+
+#[rstest]
+#[case("rye = 1\n")]
+#[case("rye\t=\u{000C}1  # bread  \n")]
+#[case("if rye:\n    bread\n  \n\t# cheese\n    toast\nspam  ")]
+#[case("if rye:\n    if bread:\n        cheese\n    toast\n")]
+#[case("rye = 1 + \\\n    2 \\\r\n+ 3\r\n")]
+#[case("\\\nrye\n")]
+#[case("rye(\n    bread,\n    cheese ,\n)\n")]
+#[case("f'{ rye = !r:>{ width }}' \"\"\"bread\n  cheese\"\"\"\n")]
+#[case("   \n")]
+#[case("")]
+fn tokens_join_into_source(#[case] source: &str) {
+    let tokens = tokenize(source, trivia());
+    let joined: String = tokens
+        .iter()
+        .map(|token| token.token_contents.as_str())
+        .collect();
+    assert_eq!(joined, source);
+    // every token starts where the last ended
+    let mut end = 0;
+    for token in tokens.iter() {
+        assert_eq!(token.byte_start, end, "{:?} is not where expected", token);
+        end = token.byte_end;
+    }
+}
+
+#[rstest]
+#[case("rye = 1\n")]
+#[case("if rye:\n    bread\n  \n\t# cheese\n    toast\nspam  ")]
+#[case("rye = 1 + \\\n    2 \\\r\n+ 3\r\n")]
+#[case("f'{ rye = !r:>{ width }}'\n")]
+fn trivia_adds_only_trivia(#[case] source: &str) {
+    let without: Vec<Token> = tokenize(source, trivia())
+        .into_iter()
+        .filter(|token| !is_trivia(token))
+        .collect();
+    assert_eq!(without, tokenize(source, TokenizerOptions::default()));
+}
+
+#[test]
+fn trivia_tokens() {
+    let found: Vec<(TokenType, String)> = tokenize("if rye:\n  bread \\\n\n", trivia())
+        .into_iter()
+        .map(|token| (token.token_type, token.token_contents))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (TokenType::NAME, String::from("if")),
+            (TokenType::WHITESPACE, String::from(" ")),
+            (TokenType::NAME, String::from("rye")),
+            (TokenType::OP, String::from(":")),
+            (TokenType::NEWLINE, String::from("\n")),
+            (TokenType::INDENT, String::from("  ")),
+            (TokenType::NAME, String::from("bread")),
+            (TokenType::WHITESPACE, String::from(" ")),
+            (TokenType::CONTINUATION, String::from("\\\n")),
+            (TokenType::NEWLINE, String::from("\n")),
+            (TokenType::DEDENT, String::from("")),
+            (TokenType::ENDMARKER, String::from("")),
+        ]
+    );
+}
@@ -0,0 +1,164 @@
+use rstest::*;
+
+use rye::tokenize::{untokenize, TokenizerOptions};
+use rye::tokens::{Token, TokenType};
+
+pub mod common;
+use common::tokenize;
+
+/// the type and contents of every token, which is all that must survive untokenizing
+fn contents(tokens: &[Token]) -> Vec<(TokenType, &str)> {
+    tokens
+        .iter()
+        .map(|token| (token.exact_token_type, token.token_contents.as_str()))
+        .collect()
+}
+
+/// forget where every token was found
+fn without_positions(tokens: Vec<Token>) -> Vec<Token> {
+    tokens
+        .into_iter()
+        .map(|token| Token {
+            start: (0, 0),
+            end: (0, 0),
+            ..token
+        })
+        .collect()
+}
+
+#[rstest]
+#[case("rye = 1\n")]
+#[case("if rye:\n    bread(1, 2)  # cheese\n\n    \n  # toast\n    spam\nham\n")]
+#[case("if rye:\n\tbread\n")]
+#[case("rye = (1 +\\\n    2)\n")]
+#[case("rye = '''bread\n  cheese''' + \"\"\n")]
+#[case("rye(\n  bread,\n    cheese)\n")]
+#[case("f'{rye!r:>{width}} {{bread}}'\n")]
+#[case("t'{rye!r:>{width}} {{bread}}' t''\n")]
+#[case("rye\r\nbread\r\n")]
+#[case("rye")]
+fn positions_reproduce_source(#[case] source: &str) {
+    assert_eq!(
+        untokenize(tokenize(source, TokenizerOptions::default())),
+        source
+    );
+}
+
+#[rstest]
+#[case("rye\t=\u{000C}1 \\\n  + 2  # bread\n")]
+#[case("if rye:\n    bread\n  \n\t# cheese\n    toast\nspam  ")]
+fn trivia_reproduces_source(#[case] source: &str) {
+    let options = TokenizerOptions {
+        trivia: true,
+        ..TokenizerOptions::default()
+    };
+    assert_eq!(untokenize(tokenize(source, options)), source);
+}
+
+#[test]
+fn compatible_tokens_reproduce_source() {
+    let source = "f'{{rye}} {bread}'\n";
+    let options = TokenizerOptions {
+        cpython_compat: true,
+        ..TokenizerOptions::default()
+    };
+    assert_eq!(untokenize(tokenize(source, options)), source);
+}
+
+#[rstest]
+#[case("f'rye{{bread'\n")]
+#[case("f'{{rye}} {bread}'\n")]
+#[case("f'}}}}{{{rye}'\n")]
+#[case("t'{{rye}}'\n")]
+fn compatible_tokens_without_positions(#[case] source: &str) {
+    let options = TokenizerOptions {
+        cpython_compat: true,
+        ..TokenizerOptions::default()
+    };
+    let tokens = tokenize(source, options);
+    let untokenized = untokenize(without_positions(tokens.clone()));
+    assert_eq!(untokenized, source);
+    assert_eq!(
+        contents(&tokenize(&untokenized, options)),
+        contents(&tokens)
+    );
+}
+
+#[rstest]
+#[case("rye = 1\n", "rye=1\n")]
+#[case(
+    "if rye:\n    bread = cheese (1, -2)\nelse:\n    pass\n",
+    "if rye:\n    bread=cheese(1,-2)\nelse:\n    pass\n"
+)]
+#[case("rye = 1 .real + 2. + x.y\n", "rye=1 .real+2.+x.y\n")]
+#[case(
+    "rye = b'bread' 'cheese' f'{toast}'\n",
+    "rye=b'bread' 'cheese' f'{toast}'\n"
+)]
+#[case("rye ** -1 // + 2 < > 3\n", "rye**-1//+2< >3\n")]
+#[case("rye = 1 if bread else 2\n", "rye=1 if bread else 2\n")]
+fn minimal_spacing(#[case] source: &str, #[case] expected: &str) {
+    let tokens = tokenize(source, TokenizerOptions::default());
+    let untokenized = untokenize(without_positions(tokens.clone()));
+    assert_eq!(untokenized, expected);
+    assert_eq!(
+        contents(&tokenize(&untokenized, TokenizerOptions::default())),
+        contents(&tokens)
+    );
+}
+
+#[test]
+fn empty_indents_are_four_spaces_deeper() {
+    let tokens = without_positions(tokenize(
+        "if rye:\n\tif bread:\n\t\tcheese\n",
+        TokenizerOptions::default(),
+    ))
+    .into_iter()
+    .map(|token| match token.token_type {
+        TokenType::INDENT => Token {
+            token_contents: String::new(),
+            ..token
+        },
+        _ => token,
+    });
+    assert_eq!(
+        untokenize(tokens),
+        "if rye:\n    if bread:\n        cheese\n"
+    );
+}
+
+#[test]
+fn rename_keeps_spacing() {
+    let source = "rye = rye(1) + rye  # rye\nrye\n";
+    let tokens = tokenize(source, TokenizerOptions::default())
+        .into_iter()
+        .map(|token| match token.token_contents.as_str() {
+            "rye" => Token {
+                token_contents: String::from("bread_and_cheese"),
+                ..token
+            },
+            _ => token,
+        });
+    assert_eq!(
+        untokenize(tokens),
+        "bread_and_cheese = bread_and_cheese(1) + bread_and_cheese  # rye\nbread_and_cheese\n"
+    );
+}
+
+#[test]
+fn rewrite_not_equal() {
+    let options = TokenizerOptions {
+        barry_as_flufl: true,
+        ..TokenizerOptions::default()
+    };
+    let tokens = tokenize("if rye <> bread:\n    pass\n", options)
+        .into_iter()
+        .map(|token| match token.exact_token_type {
+            TokenType::NOTEQUAL => Token {
+                token_contents: String::from("!="),
+                ..token
+            },
+            _ => token,
+        });
+    assert_eq!(untokenize(tokens), "if rye != bread:\n    pass\n");
+}
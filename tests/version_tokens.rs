@@ -0,0 +1,149 @@
+use rstest::*;
+
+use rye::tokenize::{TokenStream, TokenizeError, TokenizerOptions};
+use rye::tokens::{Token, TokenType};
+
+type Found<'a> = (TokenType, &'a str);
+
+/// tokenize source as the given version of Python would
+/// return the type and contents of every token up to the first NEWLINE
+fn versioned_tokens(source: &str, options: TokenizerOptions) -> Vec<(TokenType, String)> {
+    TokenStream::with_options(source, options)
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap()
+        .into_iter()
+        .take_while(|token| token.token_type != TokenType::NEWLINE)
+        .map(|token| match token.token_type {
+            TokenType::OP => (token.exact_token_type, token.token_contents),
+            _ => (token.token_type, token.token_contents),
+        })
+        .collect()
+}
+
+fn version(python_version: (u8, u8)) -> TokenizerOptions {
+    TokenizerOptions {
+        python_version,
+        ..TokenizerOptions::default()
+    }
+}
+
+#[rstest]
+#[case("rye <> bread", version((3, 14)), &[(TokenType::NAME, "rye"), (TokenType::LESS, "<"), (TokenType::GREATER, ">"), (TokenType::NAME, "bread")])]
+#[case(
+    "rye <> bread",
+    TokenizerOptions { barry_as_flufl: true, ..TokenizerOptions::default() },
+    &[(TokenType::NAME, "rye"), (TokenType::NOTEQUAL, "<>"), (TokenType::NAME, "bread")]
+)]
+#[case("rye := 1", version((3, 7)), &[(TokenType::NAME, "rye"), (TokenType::COLON, ":"), (TokenType::EQUAL, "="), (TokenType::NUMBER, "1")])]
+#[case("rye := 1", version((3, 8)), &[(TokenType::NAME, "rye"), (TokenType::COLONEQUAL, ":="), (TokenType::NUMBER, "1")])]
+#[case("rye @= 1", version((3, 4)), &[(TokenType::NAME, "rye"), (TokenType::AT, "@"), (TokenType::EQUAL, "="), (TokenType::NUMBER, "1")])]
+#[case("rye @= 1", version((3, 5)), &[(TokenType::NAME, "rye"), (TokenType::ATEQUAL, "@="), (TokenType::NUMBER, "1")])]
+#[case("1_000", version((3, 5)), &[(TokenType::NUMBER, "1"), (TokenType::NAME, "_000")])]
+#[case("1_000", version((3, 6)), &[(TokenType::NUMBER, "1_000")])]
+#[case("0_0", version((3, 5)), &[(TokenType::NUMBER, "0"), (TokenType::NAME, "_0")])]
+#[case(
+    "f'{rye}'",
+    TokenizerOptions { python_version: (3, 5), cpython_compat: true, ..TokenizerOptions::default() },
+    &[(TokenType::NAME, "f"), (TokenType::STRING, "'{rye}'")]
+)]
+#[case("f'{rye}'", version((3, 11)), &[(TokenType::STRING, "f'{rye}'")])]
+#[case(
+    "f'{rye}'",
+    version((3, 12)),
+    &[
+        (TokenType::FSTRING_START, "f'"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::NAME, "rye"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::FSTRING_END, "'"),
+    ]
+)]
+#[case("async def rye(): await bread", version((3, 4)), &[
+    (TokenType::NAME, "async"),
+    (TokenType::NAME, "def"),
+    (TokenType::NAME, "rye"),
+    (TokenType::LPAR, "("),
+    (TokenType::RPAR, ")"),
+    (TokenType::COLON, ":"),
+    (TokenType::NAME, "await"),
+    (TokenType::NAME, "bread"),
+])]
+#[case("async def rye(): await bread", version((3, 5)), &[
+    (TokenType::ASYNC, "async"),
+    (TokenType::NAME, "def"),
+    (TokenType::NAME, "rye"),
+    (TokenType::LPAR, "("),
+    (TokenType::RPAR, ")"),
+    (TokenType::COLON, ":"),
+    (TokenType::AWAIT, "await"),
+    (TokenType::NAME, "bread"),
+])]
+#[case("async def rye(): await bread", version((3, 7)), &[
+    (TokenType::NAME, "async"),
+    (TokenType::NAME, "def"),
+    (TokenType::NAME, "rye"),
+    (TokenType::LPAR, "("),
+    (TokenType::RPAR, ")"),
+    (TokenType::COLON, ":"),
+    (TokenType::NAME, "await"),
+    (TokenType::NAME, "bread"),
+])]
+#[case("async = await", version((3, 6)), &[(TokenType::NAME, "async"), (TokenType::EQUAL, "="), (TokenType::NAME, "await")])]
+#[case("u'rye'", version((3, 3)), &[(TokenType::STRING, "u'rye'")])]
+#[case("t'rye'", version((3, 14)), &[(TokenType::TSTRING_START, "t'"), (TokenType::TSTRING_MIDDLE, "rye"), (TokenType::TSTRING_END, "'")])]
+#[case(
+    "t'rye'",
+    TokenizerOptions { python_version: (3, 13), cpython_compat: true, ..TokenizerOptions::default() },
+    &[(TokenType::NAME, "t"), (TokenType::STRING, "'rye'")]
+)]
+fn versioned_statement(
+    #[case] source: &str,
+    #[case] options: TokenizerOptions,
+    #[case] expected: &[Found],
+) {
+    let found = versioned_tokens(source, options);
+    let found: Vec<Found> = found
+        .iter()
+        .map(|(token_type, contents)| (*token_type, contents.as_str()))
+        .collect();
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn async_def_body() {
+    let source =
+        "async def rye():\n    await bread\n    async with cheese:\n        pass\nawait = async\n";
+    let found: Vec<(TokenType, String)> = TokenStream::with_options(source, version((3, 6)))
+        .collect::<Result<Vec<Token>, TokenizeError>>()
+        .unwrap()
+        .into_iter()
+        .filter(|token| token.token_contents == "async" || token.token_contents == "await")
+        .map(|token| (token.token_type, token.token_contents))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (TokenType::ASYNC, String::from("async")),
+            (TokenType::AWAIT, String::from("await")),
+            (TokenType::ASYNC, String::from("async")),
+            (TokenType::NAME, String::from("await")),
+            (TokenType::NAME, String::from("async")),
+        ]
+    );
+}
+
+#[rstest]
+#[case("u'rye'", (3, 2), "u")]
+#[case("rb'rye'", (3, 2), "rb")]
+#[case("f'{rye}'", (3, 5), "f")]
+#[case("t'{rye}'", (3, 13), "t")]
+fn prefix_too_new(#[case] source: &str, #[case] python_version: (u8, u8), #[case] prefix: &str) {
+    assert_eq!(
+        TokenStream::with_options(source, version(python_version))
+            .collect::<Result<Vec<Token>, TokenizeError>>(),
+        Err(TokenizeError::InvalidStringPrefix {
+            prefix: String::from(prefix),
+            start: (1, 0)
+        })
+    );
+}